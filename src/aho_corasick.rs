@@ -0,0 +1,88 @@
+use super::allocator::Allocator;
+use super::Strings;
+
+use alloc::vec::Vec;
+
+use aho_corasick::{AhoCorasick, Input};
+
+impl<A: Allocator> Strings<A> {
+    /// Run `ac` once over `as_str()` and return the sorted indices of the
+    /// strings containing a match of any pattern.
+    ///
+    /// Matches spanning more than one string are ignored.
+    ///
+    /// Panics if `ac` does not support unanchored searches.
+    pub fn find_entries_ac(&self, ac: &AhoCorasick) -> Vec<u32> {
+        if ac.min_pattern_len() == 0 {
+            return (0..self.len()).collect();
+        }
+
+        let haystack = self.as_str();
+        let mut indices = Vec::new();
+        let mut pos = 0;
+
+        while let Some(m) = ac.find(Input::new(haystack).range(pos..)) {
+            // Patterns are not empty, so `m.start()` is within `haystack`.
+            let (index, range) = self.entry_at_byte(m.start()).unwrap();
+
+            // A leftmost match may span the end of the string while a
+            // shorter or later one fits in it, so search again within it.
+            if m.end() <= range.end
+                || ac
+                    .find(Input::new(haystack).range(m.start()..range.end))
+                    .is_some()
+            {
+                indices.push(index);
+            }
+
+            pos = range.end;
+        }
+
+        indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Strings;
+
+    use aho_corasick::{AhoCorasick, MatchKind};
+
+    #[test]
+    fn test_find_entries_ac() {
+        let input = ["error: disk", "ok", "warn", "ing", "", "fatal error", "er"];
+        let strs: Strings = input.iter().collect();
+
+        let ac = AhoCorasick::new(["error", "warning", "fatal"]).unwrap();
+        assert_eq!(strs.find_entries_ac(&ac), [0, 5]);
+
+        let ac = AhoCorasick::new(["ok", "ing"]).unwrap();
+        assert_eq!(strs.find_entries_ac(&ac), [1, 3]);
+
+        let ac = AhoCorasick::new(["gfa"]).unwrap();
+        assert!(strs.find_entries_ac(&ac).is_empty());
+
+        let ac = AhoCorasick::new(["", "x"]).unwrap();
+        assert_eq!(strs.find_entries_ac(&ac), [0, 1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_find_entries_ac_leftmost() {
+        let strs: Strings = ["xabc", "d", "ab"].iter().collect();
+
+        for match_kind in [MatchKind::LeftmostFirst, MatchKind::LeftmostLongest] {
+            // "abcd" spans the first two strings and hides "bc" and "ab".
+            let ac = AhoCorasick::builder()
+                .match_kind(match_kind)
+                .build(["abcd", "bc"])
+                .unwrap();
+            assert_eq!(strs.find_entries_ac(&ac), [0]);
+
+            let ac = AhoCorasick::builder()
+                .match_kind(match_kind)
+                .build(["abcd", "ab"])
+                .unwrap();
+            assert_eq!(strs.find_entries_ac(&ac), [0, 2]);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Allocator used by the containers of this crate.
+//!
+//! With feature `allocator-api2`, `Allocator` and `Global` are re-exported
+//! from crate `allocator-api2`, so that arena and bump allocators implementing
+//! it can be used.
+//!
+//! Otherwise, a minimal fallback is used and `Global` is the only allocator.
+
+use alloc::alloc::{handle_alloc_error, Layout};
+
+use core::ptr::NonNull;
+
+#[cfg(feature = "allocator-api2")]
+pub use allocator_api2::alloc::{AllocError, Allocator, Global};
+
+#[cfg(not(feature = "allocator-api2"))]
+pub use self::inner::{AllocError, Allocator, Global};
+
+#[cfg(not(feature = "allocator-api2"))]
+mod inner {
+    use alloc::alloc::{alloc, dealloc, realloc, Layout};
+    use core::ptr::{self, NonNull};
+
+    #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+    pub struct AllocError;
+
+    /// Subset of `allocator_api2::alloc::Allocator` used by this crate.
+    ///
+    /// # Safety
+    ///
+    /// Same as `allocator_api2::alloc::Allocator`.
+    pub unsafe trait Allocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>;
+
+        /// # Safety
+        ///
+        /// `ptr` must be allocated by `self` with `layout`.
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+
+        /// # Safety
+        ///
+        /// `ptr` must be allocated by `self` with `old_layout` and
+        /// `new_layout.size()` must not be smaller than `old_layout.size()`.
+        unsafe fn grow(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError>;
+
+        /// # Safety
+        ///
+        /// `ptr` must be allocated by `self` with `old_layout` and
+        /// `new_layout.size()` must not be larger than `old_layout.size()`.
+        unsafe fn shrink(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError>;
+    }
+
+    /// The global memory allocator.
+    #[derive(Copy, Clone, Default, Debug)]
+    pub struct Global;
+
+    fn dangling(layout: Layout) -> NonNull<u8> {
+        unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+    }
+
+    unsafe impl Allocator for Global {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            let ptr = if layout.size() == 0 {
+                dangling(layout)
+            } else {
+                NonNull::new(unsafe { alloc(layout) }).ok_or(AllocError)?
+            };
+
+            Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            if layout.size() != 0 {
+                dealloc(ptr.as_ptr(), layout)
+            }
+        }
+
+        unsafe fn grow(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            self.realloc(ptr, old_layout, new_layout)
+        }
+
+        unsafe fn shrink(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            self.realloc(ptr, old_layout, new_layout)
+        }
+    }
+
+    impl Global {
+        unsafe fn realloc(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            if old_layout.size() == 0 || old_layout.align() != new_layout.align() {
+                let new_ptr = self.allocate(new_layout)?;
+                let size = old_layout.size().min(new_layout.size());
+
+                ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr() as *mut u8, size);
+                self.deallocate(ptr, old_layout);
+
+                Ok(new_ptr)
+            } else if new_layout.size() == 0 {
+                self.deallocate(ptr, old_layout);
+                Ok(NonNull::slice_from_raw_parts(dangling(new_layout), 0))
+            } else {
+                let new_ptr = realloc(ptr.as_ptr(), old_layout, new_layout.size());
+                let new_ptr = NonNull::new(new_ptr).ok_or(AllocError)?;
+
+                Ok(NonNull::slice_from_raw_parts(new_ptr, new_layout.size()))
+            }
+        }
+    }
+}
+
+/// Allocate `layout`, abort on allocation failure.
+pub(crate) fn allocate<A: Allocator>(alloc: &A, layout: Layout) -> NonNull<u8> {
+    match alloc.allocate(layout) {
+        Ok(ptr) => ptr.cast(),
+        Err(_err) => handle_alloc_error(layout),
+    }
+}
+
+/// Grow or shrink an allocation to `new_layout`, abort on allocation failure.
+///
+/// # Safety
+///
+/// `ptr` must be allocated by `alloc` with `old_layout`, which must have
+/// the same alignment as `new_layout`.
+pub(crate) unsafe fn reallocate<A: Allocator>(
+    alloc: &A,
+    ptr: NonNull<u8>,
+    old_layout: Layout,
+    new_layout: Layout,
+) -> NonNull<u8> {
+    let res = if new_layout.size() > old_layout.size() {
+        alloc.grow(ptr, old_layout, new_layout)
+    } else {
+        alloc.shrink(ptr, old_layout, new_layout)
+    };
+
+    match res {
+        Ok(ptr) => ptr.cast(),
+        Err(_err) => handle_alloc_error(new_layout),
+    }
+}
+
+/// Allocate an array of `len` elements, abort on allocation failure.
+pub(crate) fn allocate_array<T, A: Allocator>(alloc: &A, len: usize) -> NonNull<T> {
+    allocate(alloc, Layout::array::<T>(len).expect("capacity overflow")).cast()
+}
+
+/// Grow or shrink an array to `new_len` elements, abort on allocation
+/// failure.
+///
+/// # Safety
+///
+/// `ptr` must be allocated by `allocate_array` with the same `alloc` and
+/// `old_len`.
+pub(crate) unsafe fn reallocate_array<T, A: Allocator>(
+    alloc: &A,
+    ptr: NonNull<T>,
+    old_len: usize,
+    new_len: usize,
+) -> NonNull<T> {
+    let old_layout = Layout::array::<T>(old_len).unwrap();
+    let new_layout = Layout::array::<T>(new_len).expect("capacity overflow");
+
+    reallocate(alloc, ptr.cast(), old_layout, new_layout).cast()
+}
+
+/// # Safety
+///
+/// `ptr` must be allocated by `allocate_array` with the same `alloc` and `len`.
+pub(crate) unsafe fn deallocate_array<T, A: Allocator>(alloc: &A, ptr: NonNull<T>, len: usize) {
+    let layout = Layout::array::<T>(len).unwrap();
+    alloc.deallocate(ptr.cast(), layout);
+}
+
+#[cfg(all(test, feature = "allocator-api2"))]
+pub(crate) mod tests {
+    use super::{AllocError, Allocator, Global};
+
+    use std::alloc::Layout;
+    use std::cell::Cell;
+    use std::ptr::NonNull;
+
+    /// Forward to `Global` and count live allocations.
+    #[derive(Debug, Default)]
+    pub(crate) struct CountingAllocator {
+        live: Cell<usize>,
+    }
+
+    impl CountingAllocator {
+        pub(crate) fn live(&self) -> usize {
+            self.live.get()
+        }
+    }
+
+    unsafe impl Allocator for &CountingAllocator {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.live.set(self.live.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.live.set(self.live.get() - 1);
+            Global.deallocate(ptr, layout)
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use super::allocator::Allocator;
+use super::Strings;
+
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+struct BkNode {
+    /// Distance to the parent.
+    distance: u32,
+    first_child: u32,
+    next_sibling: u32,
+}
+
+/// BK-tree over the strings of a `Strings`, answering fuzzy lookups by
+/// Levenshtein distance without computing it for every string.
+///
+/// Node `i` is string `i`, so only the links between them are stored and
+/// the `Strings` it is built from must be passed to every query.
+///
+/// Distances are counted in `char`s.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct BkTree {
+    nodes: Vec<BkNode>,
+}
+
+impl BkTree {
+    pub fn new<A: Allocator>(strings: &Strings<A>) -> Self {
+        let mut nodes = Vec::with_capacity(strings.len() as usize);
+        let mut buffers = Buffers::default();
+
+        for (i, string) in strings.iter().enumerate() {
+            nodes.push(BkNode {
+                distance: 0,
+                first_child: NONE,
+                next_sibling: NONE,
+            });
+
+            if i == 0 {
+                continue;
+            }
+
+            buffers.set_query(string);
+
+            let mut parent = 0;
+            'insert: loop {
+                let distance = buffers.distance(strings.get(parent).unwrap());
+
+                let mut child = nodes[parent as usize].first_child;
+                while child != NONE {
+                    if nodes[child as usize].distance == distance {
+                        parent = child;
+                        continue 'insert;
+                    }
+                    child = nodes[child as usize].next_sibling;
+                }
+
+                let next_sibling = nodes[parent as usize].first_child;
+                nodes[parent as usize].first_child = i as u32;
+                nodes[i].distance = distance;
+                nodes[i].next_sibling = next_sibling;
+                break;
+            }
+        }
+
+        Self { nodes }
+    }
+
+    /// Only checks the length, a different `Strings` with the same length
+    /// gives wrong results.
+    fn assert_built_from<A: Allocator>(&self, strings: &Strings<A>) {
+        assert!(
+            strings.len() as usize == self.nodes.len(),
+            "BkTree is used with a Strings of different length than the one it is built from"
+        );
+    }
+
+    /// Call `f` with the index of the nodes that may be within the radius
+    /// of the query and their distance to it.
+    ///
+    /// `f` returns the radius, so that it can shrink as matches are found.
+    fn visit<A: Allocator>(
+        &self,
+        strings: &Strings<A>,
+        query: &str,
+        mut f: impl FnMut(u32, u32) -> u32,
+    ) {
+        self.assert_built_from(strings);
+
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut buffers = Buffers::default();
+        buffers.set_query(query);
+
+        let mut stack = alloc::vec![0_u32];
+        while let Some(node) = stack.pop() {
+            let distance = buffers.distance(strings.get(node).unwrap());
+            let radius = f(node, distance);
+
+            let mut child = self.nodes[node as usize].first_child;
+            while child != NONE {
+                let child_node = &self.nodes[child as usize];
+
+                // By the triangle inequality, the subtree of `child` only
+                // contains strings within `radius` if this holds.
+                if child_node.distance.abs_diff(distance) <= radius {
+                    stack.push(child);
+                }
+                child = child_node.next_sibling;
+            }
+        }
+    }
+
+    /// Return `(index, distance)` of the strings within `max_distance`
+    /// of `query`, sorted by distance and then index.
+    ///
+    /// Panics if `strings` has a different `len()` than the one `self` is
+    /// built from, any other `Strings` gives meaningless results.
+    pub fn search<A: Allocator>(
+        &self,
+        strings: &Strings<A>,
+        query: &str,
+        max_distance: u32,
+    ) -> Vec<(u32, u32)> {
+        let mut matches = Vec::new();
+
+        self.visit(strings, query, |index, distance| {
+            if distance <= max_distance {
+                matches.push((index, distance));
+            }
+            max_distance
+        });
+
+        matches.sort_unstable_by_key(|(index, distance)| (*distance, *index));
+        matches
+    }
+
+    /// Return `(index, distance)` of the `k` strings closest to `query`,
+    /// sorted by distance and then index.
+    ///
+    /// Ties are broken by the lower index.
+    ///
+    /// Panics if `strings` has a different `len()` than the one `self` is
+    /// built from, any other `Strings` gives meaningless results.
+    pub fn top_k<A: Allocator>(
+        &self,
+        strings: &Strings<A>,
+        query: &str,
+        k: usize,
+    ) -> Vec<(u32, u32)> {
+        if k == 0 {
+            self.assert_built_from(strings);
+            return Vec::new();
+        }
+
+        // Max-heap of `(distance, index)` of the best `k` found so far.
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+
+        self.visit(strings, query, |index, distance| {
+            heap.push((distance, index));
+            if heap.len() > k {
+                heap.pop();
+            }
+
+            if heap.len() < k {
+                u32::MAX
+            } else {
+                heap.peek().map(|(distance, _index)| *distance).unwrap()
+            }
+        });
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(distance, index)| (index, distance))
+            .collect()
+    }
+}
+
+/// Query and scratch space reused across distance computations.
+#[derive(Debug, Default)]
+struct Buffers {
+    query: Vec<char>,
+    row: Vec<u32>,
+}
+
+impl Buffers {
+    fn set_query(&mut self, query: &str) {
+        self.query.clear();
+        self.query.extend(query.chars());
+    }
+
+    /// Return the Levenshtein distance between the query and `s`.
+    fn distance(&mut self, s: &str) -> u32 {
+        let row = &mut self.row;
+        row.clear();
+        row.extend(0..=(self.query.len() as u32));
+
+        for (i, c) in s.chars().enumerate() {
+            // `row[j]` of the previous row, the distance between `i` chars
+            // of `s` and `j` chars of the query.
+            let mut diagonal = row[0];
+            row[0] = i as u32 + 1;
+
+            for (j, query_c) in self.query.iter().enumerate() {
+                let substitution = diagonal + (c != *query_c) as u32;
+                diagonal = row[j + 1];
+                row[j + 1] = substitution.min(diagonal + 1).min(row[j] + 1);
+            }
+        }
+
+        row[self.query.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BkTree, Buffers, Strings};
+
+    fn distance(s1: &str, s2: &str) -> u32 {
+        let mut buffers = Buffers::default();
+        buffers.set_query(s1);
+        buffers.distance(s2)
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("abc", ""), 3);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("sitting", "kitten"), 3);
+        assert_eq!(distance("flaw", "lawn"), 2);
+        assert_eq!(distance("héllo", "hello"), 1);
+    }
+
+    #[test]
+    fn test_bk_tree() {
+        let input = [
+            "push", "pop", "posh", "bush", "", "reserve", "reverse", "push", "pushed", "shrink",
+            "truncate", "append", "apend", "retain", "get",
+        ];
+        let strs: Strings = input.iter().collect();
+        let tree = BkTree::new(&strs);
+
+        for query in ["push", "puhs", "reserv", "", "apnd", "xyz", "trunk"] {
+            let mut expected: Vec<(u32, u32)> = input
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i as u32, distance(query, s)))
+                .collect();
+            expected.sort_by_key(|(index, distance)| (*distance, *index));
+
+            for max_distance in 0..4 {
+                let matches: Vec<_> = expected
+                    .iter()
+                    .copied()
+                    .filter(|(_index, distance)| *distance <= max_distance)
+                    .collect();
+                assert_eq!(tree.search(&strs, query, max_distance), matches);
+            }
+
+            for k in [0, 1, 3, input.len(), input.len() + 1] {
+                let top_k: Vec<_> = expected.iter().copied().take(k).collect();
+                assert_eq!(tree.top_k(&strs, query, k), top_k);
+            }
+        }
+
+        let empty = Strings::new();
+        let tree = BkTree::new(&empty);
+        assert!(tree.search(&empty, "a", 3).is_empty());
+        assert!(tree.top_k(&empty, "a", 3).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Strings of different length")]
+    fn test_bk_tree_different_len() {
+        let tree = BkTree::new(&["abc"].iter().collect::<Strings>());
+        tree.search(&["abc", "abd"].iter().collect::<Strings>(), "a", 1);
+    }
+}
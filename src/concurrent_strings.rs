@@ -0,0 +1,377 @@
+use super::allocator::{allocate_array, deallocate_array, Allocator, Global};
+use super::Strings;
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Debug};
+use core::ptr::{self, NonNull};
+use core::slice;
+use core::str;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+/// Bucket `b` of slots holds `1 << (SLOTS_SHIFT + b)` slots.
+const SLOTS_SHIFT: u32 = 5;
+/// Enough buckets for `u32::MAX` slots.
+const SLOT_BUCKETS: usize = (u32::BITS + 1 - SLOTS_SHIFT) as usize;
+
+/// Bucket `b` of bytes holds `1 << (BYTES_SHIFT + b)` bytes.
+const BYTES_SHIFT: u32 = 12;
+const BYTE_BUCKETS: usize = (usize::BITS - BYTES_SHIFT) as usize;
+
+/// Return the bucket and the offset in it of `pos`, where bucket `b`
+/// holds `1 << (shift + b)` elements.
+fn locate(pos: usize, shift: u32) -> (usize, usize) {
+    let biased = pos.checked_add(1 << shift).expect("capacity overflow");
+    let bucket = usize::BITS - 1 - biased.leading_zeros() - shift;
+
+    (bucket as usize, biased - (1 << (shift + bucket)))
+}
+
+fn bucket_len(bucket: usize, shift: u32) -> usize {
+    1 << (shift + bucket as u32)
+}
+
+struct Slot {
+    ptr: UnsafeCell<*const u8>,
+    len: UnsafeCell<usize>,
+    /// Set once `ptr` and `len` are written, they are never modified
+    /// afterwards.
+    ready: AtomicBool,
+}
+
+/// Append-only `Strings` that can be pushed to from many threads at once
+/// without locking.
+///
+/// Strings and their indices are stored in buckets of doubling size that
+/// are never reallocated, so pushing never moves existing strings and
+/// `get` is wait-free.
+///
+/// Can store at most `u32::MAX` strings.
+///
+/// * `A` - Allocator of the buckets.
+pub struct ConcurrentStrings<A: Allocator = Global> {
+    /// Number of indices handed out by `push`.
+    len: AtomicUsize,
+    /// Number of bytes handed out by `push`.
+    bytes_len: AtomicUsize,
+    slots: [AtomicPtr<Slot>; SLOT_BUCKETS],
+    bytes: [AtomicPtr<u8>; BYTE_BUCKETS],
+    alloc: A,
+}
+
+impl Default for ConcurrentStrings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConcurrentStrings {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<A: Allocator> ConcurrentStrings<A> {
+    pub fn new_in(alloc: A) -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const NULL_SLOTS: AtomicPtr<Slot> = AtomicPtr::new(ptr::null_mut());
+        #[allow(clippy::declare_interior_mutable_const)]
+        const NULL_BYTES: AtomicPtr<u8> = AtomicPtr::new(ptr::null_mut());
+
+        Self {
+            len: AtomicUsize::new(0),
+            bytes_len: AtomicUsize::new(0),
+            slots: [NULL_SLOTS; SLOT_BUCKETS],
+            bytes: [NULL_BYTES; BYTE_BUCKETS],
+            alloc,
+        }
+    }
+
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    /// Return the bucket, allocating and initializing it with `init` if
+    /// it does not exist yet.
+    fn get_or_alloc_bucket<T>(
+        &self,
+        bucket: &AtomicPtr<T>,
+        len: usize,
+        init: impl FnOnce(NonNull<T>),
+    ) -> NonNull<T> {
+        if let Some(ptr) = NonNull::new(bucket.load(Ordering::Acquire)) {
+            return ptr;
+        }
+
+        let new = allocate_array::<T, A>(&self.alloc, len);
+        init(new);
+
+        match bucket.compare_exchange(
+            ptr::null_mut(),
+            new.as_ptr(),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => new,
+            Err(existing) => {
+                // Another thread allocated it first.
+                unsafe { deallocate_array(&self.alloc, new, len) };
+                unsafe { NonNull::new_unchecked(existing) }
+            }
+        }
+    }
+
+    /// Reserve `len` contiguous bytes, `len` must not be 0.
+    fn reserve_bytes(&self, len: usize) -> NonNull<u8> {
+        loop {
+            let start = self.bytes_len.fetch_add(len, Ordering::Relaxed);
+            let last = start.checked_add(len - 1).expect("capacity overflow");
+
+            let (bucket, offset) = locate(start, BYTES_SHIFT);
+
+            // Ranges crossing into the next bucket are left unused, the
+            // next bucket is twice as large so this terminates.
+            if locate(last, BYTES_SHIFT).0 == bucket {
+                let ptr = self.get_or_alloc_bucket(
+                    &self.bytes[bucket],
+                    bucket_len(bucket, BYTES_SHIFT),
+                    |_| (),
+                );
+
+                return unsafe { NonNull::new_unchecked(ptr.as_ptr().add(offset)) };
+            }
+        }
+    }
+
+    /// Push `s` and return its index, it is visible to `get` once this
+    /// returns.
+    pub fn push(&self, s: &str) -> u32 {
+        let index = self.len.fetch_add(1, Ordering::Relaxed);
+        assert!(
+            index < u32::MAX as usize,
+            "ConcurrentStrings cannot contain more than u32::MAX strings"
+        );
+
+        let ptr = if s.is_empty() {
+            NonNull::dangling()
+        } else {
+            let ptr = self.reserve_bytes(s.len());
+            // The reserved bytes are only accessed by this thread until
+            // the slot is ready.
+            unsafe { ptr::copy_nonoverlapping(s.as_ptr(), ptr.as_ptr(), s.len()) };
+            ptr
+        };
+
+        let (bucket, offset) = locate(index, SLOTS_SHIFT);
+        let bucket_len = bucket_len(bucket, SLOTS_SHIFT);
+        let slots = self.get_or_alloc_bucket(&self.slots[bucket], bucket_len, |slots| {
+            for i in 0..bucket_len {
+                unsafe {
+                    slots.as_ptr().add(i).write(Slot {
+                        ptr: UnsafeCell::new(ptr::null()),
+                        len: UnsafeCell::new(0),
+                        ready: AtomicBool::new(false),
+                    })
+                };
+            }
+        });
+
+        let slot = unsafe { &*slots.as_ptr().add(offset) };
+        // Only this thread is given `index`, and readers do not access
+        // the slot until it is ready.
+        unsafe {
+            *slot.ptr.get() = ptr.as_ptr();
+            *slot.len.get() = s.len();
+        }
+        slot.ready.store(true, Ordering::Release);
+
+        index as u32
+    }
+
+    /// Return the number of indices handed out by `push`, including those
+    /// whose strings are not visible to `get` yet.
+    pub fn len(&self) -> u32 {
+        self.len.load(Ordering::Relaxed).min(u32::MAX as usize) as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the string at `index` if its `push` has completed.
+    pub fn get(&self, index: u32) -> Option<&str> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let (bucket, offset) = locate(index as usize, SLOTS_SHIFT);
+        let slots = NonNull::new(self.slots[bucket].load(Ordering::Acquire))?;
+        let slot = unsafe { &*slots.as_ptr().add(offset) };
+
+        if !slot.ready.load(Ordering::Acquire) {
+            return None;
+        }
+
+        // The slot is never modified once ready, and the bytes are valid
+        // utf-8 copied from a `&str` that live as long as `self`.
+        unsafe {
+            let bytes = slice::from_raw_parts(*slot.ptr.get(), *slot.len.get());
+            Some(str::from_utf8_unchecked(bytes))
+        }
+    }
+
+    /// Copy all strings into `Strings`, preserving their indices.
+    ///
+    /// Taking `self` guarantees that all pushes have completed.
+    pub fn into_strings(self) -> Strings {
+        let len = self.len();
+        let mut strings = Strings::with_capacity(len);
+
+        for index in 0..len {
+            strings.push(self.get(index).expect("all pushes have completed"));
+        }
+
+        strings
+    }
+}
+
+impl<A: Allocator> Debug for ConcurrentStrings<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.len()).map(|index| self.get(index)))
+            .finish()
+    }
+}
+
+impl<A: Allocator> Drop for ConcurrentStrings<A> {
+    fn drop(&mut self) {
+        for (bucket, slots) in self.slots.iter_mut().enumerate() {
+            if let Some(slots) = NonNull::new(*slots.get_mut()) {
+                unsafe { deallocate_array(&self.alloc, slots, bucket_len(bucket, SLOTS_SHIFT)) };
+            }
+        }
+
+        for (bucket, bytes) in self.bytes.iter_mut().enumerate() {
+            if let Some(bytes) = NonNull::new(*bytes.get_mut()) {
+                unsafe { deallocate_array(&self.alloc, bytes, bucket_len(bucket, BYTES_SHIFT)) };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{locate, ConcurrentStrings, BYTES_SHIFT, SLOTS_SHIFT};
+
+    use std::thread;
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate(0, SLOTS_SHIFT), (0, 0));
+        assert_eq!(locate(31, SLOTS_SHIFT), (0, 31));
+        assert_eq!(locate(32, SLOTS_SHIFT), (1, 0));
+        assert_eq!(locate(95, SLOTS_SHIFT), (1, 63));
+        assert_eq!(locate(96, SLOTS_SHIFT), (2, 0));
+        assert_eq!(locate(u32::MAX as usize - 1, SLOTS_SHIFT).0, 27);
+
+        assert_eq!(locate(4095, BYTES_SHIFT), (0, 4095));
+        assert_eq!(locate(4096, BYTES_SHIFT), (1, 0));
+    }
+
+    #[test]
+    fn test_concurrent_strings() {
+        let strs = ConcurrentStrings::new();
+        assert!(strs.is_empty());
+        assert_eq!(strs.get(0), None);
+
+        assert_eq!(strs.push("a"), 0);
+        assert_eq!(strs.push(""), 1);
+        // Longer than the first byte bucket.
+        let long = "x".repeat(10000);
+        assert_eq!(strs.push(&long), 2);
+        assert_eq!(strs.push("bc"), 3);
+
+        assert_eq!(strs.len(), 4);
+        assert_eq!(strs.get(0), Some("a"));
+        assert_eq!(strs.get(1), Some(""));
+        assert_eq!(strs.get(2), Some(long.as_str()));
+        assert_eq!(strs.get(3), Some("bc"));
+        assert_eq!(strs.get(4), None);
+
+        let strings = strs.into_strings();
+        assert!(strings.iter().eq(["a", "", long.as_str(), "bc"]));
+    }
+
+    #[test]
+    fn test_concurrent_push() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 2000;
+
+        let strs = ConcurrentStrings::new();
+
+        let pushed: Vec<Vec<(u32, String)>> = thread::scope(|scope| {
+            let reader = scope.spawn(|| {
+                // Published strings never change.
+                while strs.len() < (THREADS * PER_THREAD) as u32 {
+                    for index in 0..strs.len() {
+                        if let Some(s) = strs.get(index) {
+                            assert!(s.starts_with('t'));
+                        }
+                    }
+                }
+            });
+
+            let writers: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let strs = &strs;
+                    scope.spawn(move || {
+                        (0..PER_THREAD)
+                            .map(|i| {
+                                let s = format!("t{}-{}", t, "y".repeat(i % 50));
+                                let index = strs.push(&s);
+                                assert_eq!(strs.get(index), Some(s.as_str()));
+                                (index, s)
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+
+            let pushed = writers.into_iter().map(|w| w.join().unwrap()).collect();
+            reader.join().unwrap();
+            pushed
+        });
+
+        assert_eq!(strs.len() as usize, THREADS * PER_THREAD);
+        for (index, s) in pushed.iter().flatten() {
+            assert_eq!(strs.get(*index), Some(s.as_str()));
+        }
+
+        let strings = strs.into_strings();
+        for (index, s) in pushed.iter().flatten() {
+            assert_eq!(strings.get(*index), Some(s.as_str()));
+        }
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn test_allocator() {
+        use crate::allocator::tests::CountingAllocator;
+
+        let alloc = CountingAllocator::default();
+
+        let strs = ConcurrentStrings::new_in(&alloc);
+        assert_eq!(alloc.live(), 0);
+
+        strs.push("a");
+        strs.push("");
+        assert_eq!(alloc.live(), 2);
+
+        for n in 0..100 {
+            strs.push(&n.to_string());
+        }
+        assert_eq!(alloc.live(), 4);
+
+        drop(strs);
+        assert_eq!(alloc.live(), 0);
+    }
+}
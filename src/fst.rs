@@ -0,0 +1,182 @@
+use super::allocator::Allocator;
+use super::Strings;
+
+use std::io;
+
+use fst::automaton::{Levenshtein, LevenshteinError, StartsWith, Str};
+use fst::map::StreamBuilder;
+use fst::{Automaton, Error, Map, MapBuilder, Streamer};
+
+/// Finite state transducer mapping the strings of a sorted `Strings`
+/// to `u32` values, wrapping `fst::Map`.
+///
+/// Streams are those of `fst` and yield the values as `u64`, iterate them
+/// with `fst::Streamer`.
+#[derive(Clone)]
+pub struct StringsFst {
+    map: Map<Vec<u8>>,
+}
+
+impl StringsFst {
+    /// Map each string to its index.
+    ///
+    /// `strings` must be sorted and contain no duplicates.
+    pub fn new<A: Allocator>(strings: &Strings<A>) -> Result<Self, Error> {
+        let map = Map::from_iter(strings.iter().zip(0..).map(|(s, i)| (s, i as u64)))?;
+        Ok(Self { map })
+    }
+
+    /// Map the `i`-th string to `values[i]`.
+    ///
+    /// `strings` must be sorted and contain no duplicates.
+    ///
+    /// Panics if `values` does not have one value per string.
+    pub fn with_values<A: Allocator>(strings: &Strings<A>, values: &[u32]) -> Result<Self, Error> {
+        assert_eq!(strings.len() as usize, values.len());
+
+        let mut builder = MapBuilder::memory();
+        for (s, value) in strings.iter().zip(values) {
+            builder.insert(s, *value as u64)?;
+        }
+
+        let map = Map::new(builder.into_inner()?)?;
+        Ok(Self { map })
+    }
+
+    /// Load `bytes` returned by `StringsFst::into_bytes` or `as_bytes`.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        let map = Map::new(bytes)?;
+
+        let mut stream = map.stream();
+        while let Some((_key, value)) = stream.next() {
+            if value > u32::MAX as u64 {
+                return Err(
+                    io::Error::new(io::ErrorKind::InvalidData, "value overflows u32").into(),
+                );
+            }
+        }
+
+        Ok(Self { map })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.map.as_fst().as_bytes()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.map.into_fst().into_inner()
+    }
+
+    pub fn as_map(&self) -> &Map<Vec<u8>> {
+        &self.map
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<u32> {
+        self.map.get(key).map(|value| value as u32)
+    }
+
+    /// Stream the keys starting with `prefix` in order.
+    pub fn prefix<'a>(&'a self, prefix: &'a str) -> StreamBuilder<'a, StartsWith<Str<'a>>> {
+        self.map.search(Str::new(prefix).starts_with())
+    }
+
+    /// Stream the keys in order, restricted with `ge`, `gt`, `le` and `lt`
+    /// on the returned builder.
+    pub fn range(&self) -> StreamBuilder<'_> {
+        self.map.range()
+    }
+
+    /// Stream the keys within Levenshtein distance `max_distance` of
+    /// `query` in order.
+    ///
+    /// Fails if the automaton for `query` is too large to build.
+    pub fn fuzzy(
+        &self,
+        query: &str,
+        max_distance: u32,
+    ) -> Result<StreamBuilder<'_, Levenshtein>, LevenshteinError> {
+        Ok(self.map.search(Levenshtein::new(query, max_distance)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Automaton, StreamBuilder, Strings, StringsFst};
+
+    use fst::IntoStreamer;
+
+    fn collect<A: Automaton>(stream: StreamBuilder<'_, A>) -> Vec<(String, u32)> {
+        let items = stream.into_stream().into_str_vec().unwrap();
+        items.into_iter().map(|(s, v)| (s, v as u32)).collect()
+    }
+
+    fn owned(items: &[(&str, u32)]) -> Vec<(String, u32)> {
+        items.iter().map(|(s, v)| (s.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn test_strings_fst() {
+        let input = ["", "app", "append", "apply", "b", "ba", "bb", "c"];
+        let strs: Strings = input.iter().collect();
+        let fst = StringsFst::new(&strs).unwrap();
+
+        assert_eq!(fst.len(), input.len());
+        for (i, s) in input.iter().enumerate() {
+            assert_eq!(fst.get(s), Some(i as u32));
+        }
+        assert_eq!(fst.get("ap"), None);
+        assert!(!fst.contains_key("d"));
+
+        assert_eq!(
+            collect(fst.prefix("app")),
+            owned(&[("app", 1), ("append", 2), ("apply", 3)])
+        );
+        assert_eq!(
+            collect(fst.range().ge("apply").lt("bb")),
+            owned(&[("apply", 3), ("b", 4), ("ba", 5)])
+        );
+        assert_eq!(
+            collect(fst.fuzzy("appl", 1).unwrap()),
+            owned(&[("app", 1), ("apply", 3)])
+        );
+
+        let bytes = fst.clone().into_bytes();
+        assert_eq!(bytes, fst.as_bytes());
+        let fst = StringsFst::from_bytes(bytes).unwrap();
+        assert_eq!(fst.get("ba"), Some(5));
+
+        assert!(StringsFst::from_bytes(vec![1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_strings_fst_with_values() {
+        let strs: Strings = ["a", "b", "c"].iter().collect();
+        let fst = StringsFst::with_values(&strs, &[30, 10, u32::MAX]).unwrap();
+
+        assert_eq!(
+            collect(fst.range()),
+            owned(&[("a", 30), ("b", 10), ("c", u32::MAX)])
+        );
+
+        let big = fst::Map::from_iter([("a", u32::MAX as u64 + 1)]).unwrap();
+        assert!(StringsFst::from_bytes(big.into_fst().into_inner()).is_err());
+    }
+
+    #[test]
+    fn test_strings_fst_unsorted() {
+        assert!(StringsFst::new(&["b", "a"].iter().collect::<Strings>()).is_err());
+        assert!(StringsFst::new(&["a", "a"].iter().collect::<Strings>()).is_err());
+    }
+}
@@ -1,14 +1,68 @@
 //!  This crate contains feature `serde`, which enables serialization/deserialization
 //!  support.
+//!
+//!  Feature `allocator-api2` enables custom allocators implementing
+//!  `allocator_api2::alloc::Allocator`, otherwise only `Global` can be used.
+//!
+//!  Features `aho-corasick` and `regex` enable matching `Strings` against
+//!  multiple patterns and regular expressions.
+//!
+//!  Feature `fst` enables `StringsFst`, a finite state transducer built
+//!  from sorted `Strings`.
+//!
+//!  Feature `rayon` enables parallel iteration and construction of `Strings`.
+//!
+//!  Feature `std` is enabled by default, without it this crate is `no_std`
+//!  and only depends on `alloc`.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "aho-corasick")]
+mod aho_corasick;
+mod allocator;
+mod bk_tree;
+mod concurrent_strings;
+#[cfg(feature = "fst")]
+mod fst;
+#[cfg(feature = "rayon")]
+mod rayon;
+#[cfg(feature = "regex")]
+mod regex;
 #[cfg(feature = "serde")]
 mod serde;
 mod small_array_box;
+mod small_array_vec;
+mod small_str;
+mod str_list;
+mod str_pairs;
 mod strings;
+mod strings_arena;
+mod strings_find;
 mod strings_no_index;
+mod suffix_index;
+mod thin_header;
+mod thin_small_array_box;
+mod thin_vec_in;
 mod two_strs;
 
-pub use small_array_box::SmallArrayBox;
+#[cfg(feature = "fst")]
+pub use self::fst::StringsFst;
+#[cfg(feature = "rayon")]
+pub use self::rayon::StringsParIter;
+pub use allocator::{AllocError, Allocator, Global};
+pub use bk_tree::BkTree;
+pub use concurrent_strings::ConcurrentStrings;
+pub use small_array_box::{SmallArrayBox, SmallArrayBoxIntoIter};
+pub use small_array_vec::SmallArrayVec;
+pub use small_str::SmallStr;
+pub use str_list::{IndexedStrList, StrList};
+pub use str_pairs::*;
 pub use strings::*;
+pub use strings_arena::{StringsArena, StringsArenaIter};
+pub use strings_find::{StringsFindEntries, StringsFindIter};
 pub use strings_no_index::*;
+pub use suffix_index::SuffixIndex;
+pub use thin_small_array_box::ThinSmallArrayBox;
 pub use two_strs::*;
@@ -0,0 +1,226 @@
+use super::allocator::Allocator;
+use super::{Strings, StringsIter};
+
+use alloc::collections::LinkedList;
+use alloc::vec::Vec;
+
+use rayon::iter::plumbing::{bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer};
+use rayon::iter::{
+    FromParallelIterator, IndexedParallelIterator, IntoParallelIterator, ParallelExtend,
+    ParallelIterator,
+};
+use rayon::slice::ParallelSliceMut;
+
+impl<'a, A: Allocator> IntoParallelIterator for &'a Strings<A> {
+    type Item = &'a str;
+    type Iter = StringsParIter<'a>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        let (strs, ends) = self.raw_parts();
+        StringsParIter(StringsProducer {
+            strs,
+            ends,
+            start: 0,
+        })
+    }
+}
+
+/// Parallel iterator of `Strings`, created by `Strings::par_iter`.
+///
+/// It is split on `ends`, so every split is O(1).
+#[derive(Clone, Debug)]
+pub struct StringsParIter<'a>(StringsProducer<'a>);
+
+#[derive(Clone, Debug)]
+struct StringsProducer<'a> {
+    strs: &'a [u8],
+    ends: &'a [u32],
+    /// Start of the string ending at `ends[0]`.
+    start: u32,
+}
+
+impl<'a> ParallelIterator for StringsParIter<'a> {
+    type Item = &'a str;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.0.ends.len())
+    }
+}
+
+impl IndexedParallelIterator for StringsParIter<'_> {
+    fn len(&self) -> usize {
+        self.0.ends.len()
+    }
+
+    fn drive<C: Consumer<Self::Item>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<Self::Item>>(self, callback: CB) -> CB::Output {
+        callback.callback(self.0)
+    }
+}
+
+impl<'a> Producer for StringsProducer<'a> {
+    type Item = &'a str;
+    type IntoIter = StringsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        StringsIter::new(self.strs, self.ends, self.start)
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let (left, right) = self.ends.split_at(index);
+        let mid = left.last().copied().unwrap_or(self.start);
+
+        (
+            Self {
+                strs: self.strs,
+                ends: left,
+                start: self.start,
+            },
+            Self {
+                strs: self.strs,
+                ends: right,
+                start: mid,
+            },
+        )
+    }
+}
+
+/// Parallel counterpart of `Extend<S>`, produces the same strings in the
+/// same order.
+///
+/// Each thread collects into its own `Strings`, which are then merged
+/// in order by shifting their ends.
+impl<A: Allocator, S: AsRef<str> + Send> ParallelExtend<S> for Strings<A> {
+    fn par_extend<I: IntoParallelIterator<Item = S>>(&mut self, par_iter: I) {
+        let list: LinkedList<Strings> = par_iter
+            .into_par_iter()
+            .fold(Strings::new, |mut strings, s| {
+                strings.push(s.as_ref());
+                strings
+            })
+            .collect();
+
+        let len: usize = list.iter().map(|strings| strings.len() as usize).sum();
+        let strs_len: usize = list.iter().map(|strings| strings.strs_len() as usize).sum();
+
+        self.reserve(len.min(u32::MAX as usize) as u32);
+        self.reserve_strs(strs_len);
+
+        for strings in &list {
+            self.extend_from_strings(strings);
+        }
+    }
+}
+
+impl<S: AsRef<str> + Send> FromParallelIterator<S> for Strings {
+    fn from_par_iter<I: IntoParallelIterator<Item = S>>(par_iter: I) -> Self {
+        let mut strings = Strings::new();
+        strings.par_extend(par_iter);
+        strings
+    }
+}
+
+impl<A: Allocator + Sync> Strings<A> {
+    /// Sort the strings in parallel.
+    ///
+    /// The indices of the strings are sorted first, then the strings are
+    /// copied back in sorted order, which takes `strs_len() + 4 * len()`
+    /// extra bytes.
+    pub fn par_sort(&mut self) {
+        let mut order: Vec<u32> = (0..self.len()).collect();
+
+        let this = &*self;
+        order.par_sort_unstable_by(|i, j| this.get(*i).unwrap().cmp(this.get(*j).unwrap()));
+
+        let mut strs = Vec::with_capacity(self.strs_len() as usize);
+        for index in order.iter_mut() {
+            strs.extend_from_slice(self.get(*index).unwrap().as_bytes());
+
+            // `order` is reused to store the new ends.
+            *index = strs.len() as u32;
+        }
+
+        let (self_strs, self_ends) = self.raw_parts_mut();
+        self_strs.copy_from_slice(&strs);
+        self_ends.copy_from_slice(&order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Strings;
+
+    use rayon::iter::{
+        IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelExtend,
+        ParallelIterator,
+    };
+
+    fn get_input() -> Vec<String> {
+        (0..10000).map(|n| (n * 7919 % 10007).to_string()).collect()
+    }
+
+    #[test]
+    fn test_par_iter() {
+        let input = get_input();
+        let strings: Strings = input.iter().collect();
+
+        let collected: Vec<&str> = strings.par_iter().collect();
+        assert!(input.iter().eq(collected.iter()));
+
+        let collected: Vec<(usize, &str)> = strings.par_iter().enumerate().rev().collect();
+        assert!(collected.iter().map(|(i, s)| (*i, *s)).eq(input
+            .iter()
+            .map(String::as_str)
+            .enumerate()
+            .rev()));
+
+        let collected: Vec<&str> = strings.par_iter().skip(100).step_by(3).collect();
+        assert!(input.iter().skip(100).step_by(3).eq(collected.iter()));
+
+        assert_eq!(strings.par_iter().len(), input.len());
+        assert_eq!(Strings::new().par_iter().count(), 0);
+    }
+
+    #[test]
+    fn test_from_par_iter() {
+        let input = get_input();
+
+        let strings: Strings = input.par_iter().collect();
+        assert!(input.iter().eq(strings.iter()));
+
+        let mut strings: Strings = input[..10].iter().collect();
+        strings.par_extend(input.clone().into_par_iter());
+        assert_eq!(strings.len() as usize, input.len() + 10);
+        assert!(strings.iter().skip(10).eq(input.iter()));
+        assert!(strings.iter().take(10).eq(input[..10].iter()));
+    }
+
+    #[test]
+    fn test_par_sort() {
+        let mut input = get_input();
+        let mut strings: Strings = input.iter().collect();
+
+        strings.par_sort();
+        input.sort_unstable();
+
+        assert!(input.iter().eq(strings.iter()));
+
+        let mut strings: Strings = ["b", "", "ab", "b", "a", ""].iter().collect();
+        strings.par_sort();
+        assert!(strings.iter().eq(["", "", "a", "ab", "b", "b"]));
+
+        let mut strings = Strings::new();
+        strings.par_sort();
+        assert!(strings.is_empty());
+    }
+}
@@ -0,0 +1,131 @@
+use super::allocator::Allocator;
+use super::Strings;
+
+use alloc::vec::Vec;
+
+use regex::Regex;
+
+/// Return true if `regex` may contain look-around assertions, such as
+/// `^`, `$` and `\b`, whose result depends on the bytes around a match.
+fn has_look_around(regex: &Regex) -> bool {
+    // Patterns that only parse with `RegexBuilder` flags, such as
+    // `ignore_whitespace`, are assumed to contain them.
+    regex_syntax::parse(regex.as_str()).map_or(true, |hir| !hir.properties().look_set().is_empty())
+}
+
+impl<A: Allocator> Strings<A> {
+    /// Return the sorted indices of the strings matching `regex`.
+    ///
+    /// Like `Strings::find_entries_ac`, `regex` runs once over `as_str()`
+    /// and matches spanning more than one string are ignored.
+    ///
+    /// If `regex` contains look-around assertions, such as `^`, `$` and
+    /// `\b`, it runs on each string separately instead, so that they match
+    /// at the boundaries of the strings as they would in `regex.is_match(s)`.
+    pub fn find_entries_regex(&self, regex: &Regex) -> Vec<u32> {
+        if has_look_around(regex) {
+            return self
+                .iter()
+                .enumerate()
+                .filter(|(_index, s)| regex.is_match(s))
+                .map(|(index, _s)| index as u32)
+                .collect();
+        }
+
+        // Without look-around, an empty match does not depend on its
+        // position, so it is found in every string.
+        if regex.is_match("") {
+            return (0..self.len()).collect();
+        }
+
+        let haystack = self.as_str();
+        let mut indices = Vec::new();
+        let mut pos = 0;
+
+        while let Some(m) = regex.find_at(haystack, pos) {
+            // Matches are not empty, so `m.start()` is within `haystack`.
+            let (index, range) = self.entry_at_byte(m.start()).unwrap();
+
+            // The leftmost match may span the end of the string while a
+            // shorter one fits in it, so search again within it.
+            if m.end() <= range.end || regex.is_match(&haystack[m.start()..range.end]) {
+                indices.push(index);
+            }
+
+            pos = range.end;
+        }
+
+        indices
+    }
+
+    /// Keep only the strings matching `regex`, found by
+    /// `Strings::find_entries_regex`.
+    pub fn retain_matching(&mut self, regex: &Regex) {
+        let mut matching = self.find_entries_regex(regex).into_iter().peekable();
+        let mut index = 0;
+
+        self.retain(|_s| {
+            let keep = matching.next_if_eq(&index).is_some();
+            index += 1;
+            keep
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Strings;
+
+    use regex::Regex;
+
+    #[test]
+    fn test_find_entries_regex() {
+        let strs: Strings = ["GET /a", "POST /b", "", "xGET", "GE", "T /c"]
+            .iter()
+            .collect();
+
+        let regex = Regex::new("^GET ").unwrap();
+        assert_eq!(strs.find_entries_regex(&regex), [0]);
+
+        let regex = Regex::new("GET$").unwrap();
+        assert_eq!(strs.find_entries_regex(&regex), [3]);
+
+        let regex = Regex::new("GET /c").unwrap();
+        assert!(strs.find_entries_regex(&regex).is_empty());
+
+        let regex = Regex::new("^$").unwrap();
+        assert_eq!(strs.find_entries_regex(&regex), [2]);
+    }
+
+    #[test]
+    fn test_find_entries_regex_single_pass() {
+        let strs: Strings = ["xab", "cd", "", "b", "d"].iter().collect();
+
+        // "abcd" spans the first two strings and hides "b".
+        let regex = Regex::new("abcd|b").unwrap();
+        assert_eq!(strs.find_entries_regex(&regex), [0, 3]);
+
+        let regex = Regex::new("bc|d").unwrap();
+        assert_eq!(strs.find_entries_regex(&regex), [1, 4]);
+
+        let regex = Regex::new("[a-d]{2,}").unwrap();
+        assert_eq!(strs.find_entries_regex(&regex), [0, 1]);
+
+        let regex = Regex::new("bc").unwrap();
+        assert!(strs.find_entries_regex(&regex).is_empty());
+
+        let regex = Regex::new("z*").unwrap();
+        assert_eq!(strs.find_entries_regex(&regex), [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_retain_matching() {
+        let mut strs: Strings = ["GET /a", "POST /b", "GET /c", ""].iter().collect();
+
+        strs.retain_matching(&Regex::new("^GET ").unwrap());
+        assert!(strs.iter().eq(["GET /a", "GET /c"]));
+
+        strs.retain_matching(&Regex::new("/c").unwrap());
+        assert!(strs.iter().eq(["GET /c"]));
+    }
+}
@@ -1,20 +1,94 @@
+use super::allocator::Allocator;
 use super::small_array_box::*;
-use super::{Strings, StringsIter, StringsNoIndex, StringsNoIndexIter, TwoStrs};
+use super::{
+    SmallArrayVec, SmallStr, Strings, StringsIter, StringsNoIndex, StringsNoIndexIter, SuffixIndex,
+    ThinSmallArrayBox, TwoStrs,
+};
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use core::cmp;
+use core::fmt;
+use core::iter::Iterator;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::Deref;
+
+use serde::de::{Deserialize, DeserializeSeed, Deserializer, Error, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
 
-use std::convert::TryInto;
-use std::fmt;
-use std::iter::Iterator;
-use std::marker::PhantomData;
-use std::mem::MaybeUninit;
-use std::ops::Deref;
-use std::ops::DerefMut;
+/// Cap the preallocation driven by `size_hint`, since it comes from the
+/// input and a malicious one could otherwise force a huge allocation.
+fn cautious_capacity<T>(size_hint: Option<usize>) -> usize {
+    const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
 
-use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
-use serde::ser::{Serialize, SerializeTuple, Serializer};
+    cmp::min(
+        size_hint.unwrap_or(0),
+        MAX_PREALLOC_BYTES / cmp::max(mem::size_of::<T>(), 1),
+    )
+}
+
+/// Deserialize a str and pass it to the closure, which accepts escaped
+/// strs that cannot be borrowed from the input without copying them.
+struct PushStr<F>(F);
+
+impl<'de, F: FnOnce(&str)> DeserializeSeed<'de> for PushStr<F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de, F: FnOnce(&str)> Visitor<'de> for PushStr<F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Expected str")
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<(), E> {
+        (self.0)(v);
+        Ok(())
+    }
+}
+
+/// str borrowed from the input if possible, owned otherwise.
+struct CowStr<'de>(Cow<'de, str>);
+
+impl<'de> Deserialize<'de> for CowStr<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CowStrVisitor;
+
+        impl<'de> Visitor<'de> for CowStrVisitor {
+            type Value = CowStr<'de>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "Expected str")
+            }
+
+            fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(CowStr(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(CowStr(Cow::Owned(v.into())))
+            }
+
+            fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(CowStr(Cow::Owned(v)))
+            }
+        }
+
+        deserializer.deserialize_str(CowStrVisitor)
+    }
+}
 
 macro_rules! impl_ser_de_for_strings {
     ($Strings:ident) => {
-        impl Serialize for $Strings {
+        impl<A: Allocator> Serialize for $Strings<A> {
             fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
                 serializer.collect_seq(self)
             }
@@ -35,16 +109,15 @@ macro_rules! impl_ser_de_for_strings {
                     where
                         V: SeqAccess<'de>,
                     {
-                        let len = seq.size_hint().unwrap_or(10);
+                        // `cautious_capacity` is far below `u32::MAX`.
+                        let len = cautious_capacity::<u32>(seq.size_hint()) as u32;
 
-                        let mut values =
-                            $Strings::with_capacity(len.try_into().map_err(|_| {
-                                V::Error::invalid_length(len, &"Expect u32 length")
-                            })?);
+                        let mut values = $Strings::with_capacity(len);
 
-                        while let Some(value) = seq.next_element()? {
-                            values.push(value);
-                        }
+                        while seq
+                            .next_element_seed(PushStr(|s: &str| values.push(s)))?
+                            .is_some()
+                        {}
 
                         Ok(values)
                     }
@@ -80,7 +153,7 @@ impl_Serialize_for_iter!(StringsIter);
 impl_Serialize_for_iter!(StringsNoIndexIter);
 
 /// Format: (&str, &str)
-impl Serialize for TwoStrs {
+impl<A: Allocator> Serialize for TwoStrs<A> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         self.get().serialize(serializer)
     }
@@ -89,12 +162,36 @@ impl Serialize for TwoStrs {
 /// Format: (&str, &str)
 impl<'de> Deserialize<'de> for TwoStrs {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let (s1, s2) = <(&'de str, &'de str)>::deserialize(deserializer)?;
-        Ok(Self::new(s1, s2))
+        let (s1, s2) = <(CowStr<'de>, CowStr<'de>)>::deserialize(deserializer)?;
+        Ok(Self::new(&s1.0, &s2.0))
+    }
+}
+
+/// Format: (u32, [u32])
+impl Serialize for SuffixIndex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.raw_parts().serialize(serializer)
+    }
+}
+
+/// Format: (u32, [u32])
+impl<'de> Deserialize<'de> for SuffixIndex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (len, positions) = <(u32, Vec<u32>)>::deserialize(deserializer)?;
+
+        // Queries slice `as_str()` with the positions, reject any that
+        // cannot be offsets of the indexed bytes.
+        if positions.iter().any(|pos| *pos as usize >= positions.len()) {
+            return Err(D::Error::custom("SuffixIndex position out of bounds"));
+        }
+
+        Ok(Self::from_raw_parts(len, positions))
     }
 }
 
-impl<T: Serialize, const INLINE_LEN: usize> Serialize for SmallArrayBox<T, INLINE_LEN> {
+impl<T: Serialize, const INLINE_LEN: usize, A: Allocator> Serialize
+    for SmallArrayBox<T, INLINE_LEN, A>
+{
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         self.deref().serialize(serializer)
     }
@@ -119,34 +216,63 @@ impl<'de, T: Deserialize<'de>, const INLINE_LEN: usize> Deserialize<'de>
             where
                 V: SeqAccess<'de>,
             {
-                let size_hint = seq.size_hint();
+                let mut values = SmallArrayVec::<T, INLINE_LEN>::with_capacity(
+                    cautious_capacity::<T>(seq.size_hint()),
+                );
 
-                if let Some(len) = size_hint {
-                    if len <= INLINE_LEN {
-                        let mut this = SmallArrayBox::uninit_inline_storage();
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
 
-                        let inline_storage = unsafe { this.storage.inline_storage.deref_mut() };
+                Ok(values.into_small_array_box())
+            }
+        }
 
-                        while let Some(value) = seq.next_element()? {
-                            inline_storage[this.len] = MaybeUninit::new(value);
-                            this.len += 1;
-                        }
+        deserializer.deserialize_seq(SmallArrayBoxVisitor(PhantomData))
+    }
+}
 
-                        return Ok(this);
-                    }
-                }
+impl<T: Serialize> Serialize for ThinSmallArrayBox<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.deref().serialize(serializer)
+    }
+}
 
-                let mut values = Vec::with_capacity(size_hint.unwrap_or(10));
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for ThinSmallArrayBox<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<T>::deserialize(deserializer).map(Self::from)
+    }
+}
 
-                while let Some(value) = seq.next_element()? {
-                    values.push(value);
-                }
+/// Format: &str
+impl<const INLINE_LEN: usize> Serialize for SmallStr<INLINE_LEN> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+/// Format: &str
+impl<'de, const INLINE_LEN: usize> Deserialize<'de> for SmallStr<INLINE_LEN> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SmallStrVisitor<const INLINE_LEN: usize>;
+
+        impl<'de, const INLINE_LEN: usize> Visitor<'de> for SmallStrVisitor<INLINE_LEN> {
+            type Value = SmallStr<INLINE_LEN>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "Expected str")
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(v.into())
+            }
 
-                Ok(values.into())
+            fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(v.into())
             }
         }
 
-        deserializer.deserialize_seq(SmallArrayBoxVisitor(PhantomData))
+        deserializer.deserialize_string(SmallStrVisitor)
     }
 }
 
@@ -154,17 +280,22 @@ impl<'de, T: Deserialize<'de>, const INLINE_LEN: usize> Deserialize<'de>
 mod tests {
     const INLINE_LEN: usize = 8;
 
-    use super::{Strings, StringsNoIndex, TwoStrs};
+    use super::{Strings, StringsNoIndex, SuffixIndex, TwoStrs};
     type SmallArrayBox = super::SmallArrayBox<u8, INLINE_LEN>;
+    type SmallStr = super::SmallStr<INLINE_LEN>;
+    type ThinSmallArrayBox = super::ThinSmallArrayBox<u8>;
 
     use std::error::Error;
     use std::fmt::{self, Display};
     use std::mem::MaybeUninit;
 
     use once_cell::sync::OnceCell;
-    use serde_test::{assert_ser_tokens, assert_tokens, Token};
+    use serde_test::{assert_de_tokens, assert_ser_tokens, assert_tokens, Token};
 
-    use serde::de::{self, value::SeqAccessDeserializer, Deserialize, DeserializeSeed, SeqAccess};
+    use serde::de::{
+        self, value::SeqAccessDeserializer, Deserialize, DeserializeSeed, IntoDeserializer,
+        SeqAccess,
+    };
 
     // Test using serde_test
 
@@ -296,6 +427,87 @@ mod tests {
         assert_ser_de_json!(&two_strs, TwoStrs);
     }
 
+    #[test]
+    fn test_de_escaped_json() {
+        let json = r#"["a\nb", "", "c\"d", "\u00e9"]"#;
+        let expected = ["a\nb", "", "c\"d", "\u{e9}"];
+
+        let strings: Strings = serde_json::from_str(json).unwrap();
+        assert!(strings.iter().eq(expected));
+
+        let strings: StringsNoIndex = serde_json::from_str(json).unwrap();
+        assert!(strings.iter().eq(expected));
+
+        let two_strs: TwoStrs = serde_json::from_str(r#"["k\t", "v"]"#).unwrap();
+        assert_eq!(two_strs.get(), ("k\t", "v"));
+    }
+
+    #[test]
+    fn test_de_json_reader() {
+        let strings = get_strings();
+        let json = serde_json::to_vec(strings).unwrap();
+
+        let de: Strings = serde_json::from_reader(json.as_slice()).unwrap();
+        assert_eq!(de, *strings);
+
+        let de: StringsNoIndex = serde_json::from_reader(json.as_slice()).unwrap();
+        assert!(de.iter().eq(strings.iter()));
+
+        let two_strs = TwoStrs::new("1234<<", "234a");
+        let json = serde_json::to_vec(&two_strs).unwrap();
+        let de: TwoStrs = serde_json::from_reader(json.as_slice()).unwrap();
+        assert_eq!(de, two_strs);
+    }
+
+    #[test]
+    fn test_de_owned_and_transient_strs() {
+        let mut strings = Strings::new();
+        strings.push("a");
+        strings.push("bc");
+
+        assert_de_tokens(
+            &strings,
+            &[
+                Token::Seq { len: Some(2) },
+                Token::Str("a"),
+                Token::String("bc"),
+                Token::SeqEnd,
+            ],
+        );
+
+        assert_de_tokens(
+            &TwoStrs::new("a", "bc"),
+            &[
+                Token::Tuple { len: 2 },
+                Token::String("a"),
+                Token::Str("bc"),
+                Token::TupleEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_ser_de_suffix_index() {
+        let strings = get_strings();
+        let index = SuffixIndex::new(strings);
+
+        assert_ser_de_json!(&index, SuffixIndex);
+
+        assert_tokens(
+            &SuffixIndex::new(&Strings::new()),
+            &[
+                Token::Tuple { len: 2 },
+                Token::U32(0),
+                Token::Seq { len: Some(0) },
+                Token::SeqEnd,
+                Token::TupleEnd,
+            ],
+        );
+
+        let err = serde_json::from_str::<SuffixIndex>("[1, [0, 2]]").unwrap_err();
+        assert!(err.to_string().contains("out of bounds"));
+    }
+
     #[test]
     fn test_ser_de_small_array_box_empty() {
         let tokens = [Token::Seq { len: Some(0) }, Token::SeqEnd];
@@ -333,6 +545,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ser_de_thin_small_array_box() {
+        let vec: Vec<u8> = (0..20).collect();
+
+        for len in 0..vec.len() {
+            let array = ThinSmallArrayBox::from(&vec[..len]);
+
+            let mut tokens = vec![Token::Seq { len: Some(len) }];
+            tokens.extend((0..(len as u8)).map(Token::U8));
+            tokens.push(Token::SeqEnd);
+
+            assert_tokens(&array, &tokens);
+        }
+    }
+
     #[test]
     fn test_small_array_box_de_error() {
         #[derive(Debug)]
@@ -380,4 +607,64 @@ mod tests {
             assert!(SmallArrayBox::deserialize(deserializer).is_err());
         }
     }
+
+    #[test]
+    fn test_ser_de_small_str() {
+        for s in ["", "1234", "a string longer than inline storage"] {
+            let small_str = SmallStr::from(s);
+
+            assert_tokens(&small_str, &[Token::BorrowedStr(s)]);
+            assert_tokens(&small_str, &[Token::Str(s)]);
+            assert_tokens(&small_str, &[Token::String(s)]);
+        }
+    }
+
+    #[test]
+    fn test_ser_de_serde_json_small_str() {
+        let small_str = SmallStr::from("escaped\n\"string\"");
+
+        assert_ser_de_json!(&small_str, SmallStr);
+    }
+
+    /// Yield `remaining - 1`, ..., `0`, while reporting `size_hint`.
+    struct LyingSeqAccess {
+        size_hint: Option<usize>,
+        remaining: u8,
+    }
+
+    impl<'de> SeqAccess<'de> for LyingSeqAccess {
+        type Error = de::value::Error;
+
+        fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+        where
+            T: DeserializeSeed<'de>,
+        {
+            if self.remaining == 0 {
+                Ok(None)
+            } else {
+                self.remaining -= 1;
+                seed.deserialize(self.remaining.into_deserializer())
+                    .map(Some)
+            }
+        }
+
+        fn size_hint(&self) -> Option<usize> {
+            self.size_hint
+        }
+    }
+
+    #[test]
+    fn test_small_array_box_de_wrong_size_hint() {
+        for size_hint in [None, Some(0), Some(3), Some(INLINE_LEN), Some(usize::MAX)] {
+            for len in [0, 3, INLINE_LEN as u8, INLINE_LEN as u8 + 1, 100] {
+                let deserializer = SeqAccessDeserializer::new(LyingSeqAccess {
+                    size_hint,
+                    remaining: len,
+                });
+                let array = SmallArrayBox::deserialize(deserializer).unwrap();
+
+                assert!(array.iter().copied().eq((0..len).rev()));
+            }
+        }
+    }
 }
@@ -1,32 +1,144 @@
-use std::mem::{ManuallyDrop, MaybeUninit};
-use std::ptr::NonNull;
-use std::slice::{from_raw_parts, from_raw_parts_mut};
+use super::allocator::{allocate_array, deallocate_array, reallocate_array, Allocator, Global};
+use super::SmallArrayVec;
 
-use std::iter::IntoIterator;
-use std::iter::{ExactSizeIterator, Iterator};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 
-use std::fmt::{self, Debug};
-use std::ops::{Deref, DerefMut};
+use core::mem::{self, ManuallyDrop, MaybeUninit};
+use core::num::NonZeroU8;
+use core::ptr::{self, NonNull};
+use core::slice::{self, from_raw_parts, from_raw_parts_mut};
 
-use std::cmp::{Eq, PartialEq};
+use core::iter::{DoubleEndedIterator, ExactSizeIterator, FusedIterator, Iterator};
+use core::iter::{FromIterator, IntoIterator};
 
-pub(crate) union SmallArrayBoxInner<T, const INLINE_LEN: usize> {
+use core::borrow::{Borrow, BorrowMut};
+use core::fmt::{self, Debug};
+use core::hash::{Hash, Hasher};
+use core::ops::{Deref, DerefMut};
+
+use core::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
+
+/// Number of low bytes of the heap length stored next to the pointer,
+/// the remaining 7 high bits are stored in `SmallArrayBox::tag`.
+const LEN_LOW_BYTES: usize = mem::size_of::<usize>() - 1;
+
+/// Tags smaller than `HEAP_TAG` store the inline length plus one.
+const HEAP_TAG: u8 = 0x80;
+
+/// Takes `2 * size_of::<usize>() - 1` bytes and leaves room for the tag.
+#[repr(C, packed)]
+struct HeapRepr<T> {
     ptr: NonNull<T>,
-    pub(crate) inline_storage: ManuallyDrop<[MaybeUninit<T>; INLINE_LEN]>,
+    len_low: [u8; LEN_LOW_BYTES],
+}
+
+impl<T> Clone for HeapRepr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
-/// * `INLINE_LEN` - Number of elements that can be stored inline.
-pub struct SmallArrayBox<T, const INLINE_LEN: usize> {
-    pub(crate) storage: SmallArrayBoxInner<T, INLINE_LEN>,
-    pub(crate) len: usize,
+impl<T> Copy for HeapRepr<T> {}
+
+/// Packed so that the tag can be placed right after the inline storage
+/// or the heap repr, whichever is larger.
+#[repr(C, packed)]
+union SmallArrayBoxInner<T, const INLINE_LEN: usize> {
+    heap: HeapRepr<T>,
+    inline_storage: ManuallyDrop<[MaybeUninit<T>; INLINE_LEN]>,
 }
 
-unsafe impl<T: Send, const INLINE_LEN: usize> Send for SmallArrayBox<T, INLINE_LEN> {}
-unsafe impl<T: Sync, const INLINE_LEN: usize> Sync for SmallArrayBox<T, INLINE_LEN> {}
+/// Heap storage being filled, drops the elements written so far and frees
+/// the storage if dropped, e.g. when the iterator panics.
+struct HeapGuard<T, A: Allocator> {
+    ptr: NonNull<T>,
+    cap: usize,
+    len: usize,
+    alloc: A,
+}
 
-impl<T, const INLINE_LEN: usize> Default for SmallArrayBox<T, INLINE_LEN> {
+impl<T, A: Allocator> HeapGuard<T, A> {
+    fn with_capacity_in(cap: usize, alloc: A) -> Self {
+        Self {
+            ptr: allocate_array(&alloc, cap),
+            cap,
+            len: 0,
+            alloc,
+        }
+    }
+
+    fn resize(&mut self, new_cap: usize) {
+        debug_assert!(new_cap >= self.len);
+
+        self.ptr = unsafe { reallocate_array(&self.alloc, self.ptr, self.cap, new_cap) };
+        self.cap = new_cap;
+    }
+
+    fn push(&mut self, elem: T) {
+        if self.len == self.cap {
+            self.resize(self.cap.checked_mul(2).expect("capacity overflow").max(4));
+        }
+
+        unsafe { self.ptr.as_ptr().add(self.len).write(elem) };
+        self.len += 1;
+    }
+
+    /// Return the storage, its capacity and length and the allocator.
+    fn into_raw_parts(self) -> (NonNull<T>, usize, usize, A) {
+        let this = ManuallyDrop::new(self);
+        (this.ptr, this.cap, this.len, unsafe {
+            ptr::read(&this.alloc)
+        })
+    }
+}
+
+impl<T, A: Allocator> Drop for HeapGuard<T, A> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), self.len));
+            deallocate_array(&self.alloc, self.ptr, self.cap);
+        }
+    }
+}
+
+/// * `INLINE_LEN` - Number of elements that can be stored inline,
+///   must be at most 126, otherwise using the type fails to compile.
+/// * `A` - Allocator of the heap storage.
+///
+/// The inline length is packed into a tag byte, which is also a niche,
+/// so `SmallArrayBox<u8, 15>` takes 16 bytes on 64-bit targets and
+/// `Option<SmallArrayBox<T, INLINE_LEN>>` takes no extra space.
+///
+/// The tag only has 7 bits left for the heap length, so it can contain at
+/// most `isize::MAX` elements. Creating a larger one panics, which can
+/// only happen for zero-sized `T`.
+#[repr(C)]
+pub struct SmallArrayBox<T, const INLINE_LEN: usize, A: Allocator = Global> {
+    /// Aligns `storage` for `T`, since `storage` is packed.
+    _align: [T; 0],
+    storage: SmallArrayBoxInner<T, INLINE_LEN>,
+    /// * `1..HEAP_TAG` - stored inline, the length is `tag - 1`.
+    /// * `HEAP_TAG..` - stored on heap, the low 7 bits are the high bits
+    ///   of the length.
+    tag: NonZeroU8,
+    alloc: A,
+}
+
+unsafe impl<T: Send, const INLINE_LEN: usize, A: Allocator + Send> Send
+    for SmallArrayBox<T, INLINE_LEN, A>
+{
+}
+unsafe impl<T: Sync, const INLINE_LEN: usize, A: Allocator + Sync> Sync
+    for SmallArrayBox<T, INLINE_LEN, A>
+{
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator + Default> Default
+    for SmallArrayBox<T, INLINE_LEN, A>
+{
     fn default() -> Self {
-        Self::new_empty()
+        Self::new_empty_in(A::default())
     }
 }
 
@@ -46,71 +158,411 @@ impl<T, const INLINE_LEN: usize> From<Vec<T>> for SmallArrayBox<T, INLINE_LEN> {
     }
 }
 
+impl<T, const INLINE_LEN: usize, const M: usize> From<[T; M]> for SmallArrayBox<T, INLINE_LEN> {
+    fn from(array: [T; M]) -> Self {
+        Self::new(IntoIterator::into_iter(array))
+    }
+}
+
+impl<T, const INLINE_LEN: usize> From<SmallArrayBox<T, INLINE_LEN>> for Box<[T]> {
+    fn from(array: SmallArrayBox<T, INLINE_LEN>) -> Self {
+        array.into_boxed_slice()
+    }
+}
+
+impl<T, const INLINE_LEN: usize> From<SmallArrayBox<T, INLINE_LEN>> for Vec<T> {
+    fn from(array: SmallArrayBox<T, INLINE_LEN>) -> Self {
+        array.into_boxed_slice().into_vec()
+    }
+}
+
 impl<T: Clone, const INLINE_LEN: usize> From<&[T]> for SmallArrayBox<T, INLINE_LEN> {
     fn from(slice: &[T]) -> Self {
         Self::new(slice.iter().cloned())
     }
 }
 
-impl<T: Clone, const INLINE_LEN: usize> Clone for SmallArrayBox<T, INLINE_LEN> {
+#[cfg(feature = "allocator-api2")]
+impl<T, const INLINE_LEN: usize, A: Allocator> From<allocator_api2::boxed::Box<[T], A>>
+    for SmallArrayBox<T, INLINE_LEN, A>
+{
+    fn from(boxed: allocator_api2::boxed::Box<[T], A>) -> Self {
+        Self::from_box_in(boxed)
+    }
+}
+
+#[cfg(feature = "allocator-api2")]
+impl<T, const INLINE_LEN: usize, A: Allocator> From<SmallArrayBox<T, INLINE_LEN, A>>
+    for allocator_api2::boxed::Box<[T], A>
+{
+    fn from(array: SmallArrayBox<T, INLINE_LEN, A>) -> Self {
+        array.into_boxed_slice_in()
+    }
+}
+
+impl<T, const INLINE_LEN: usize> FromIterator<T> for SmallArrayBox<T, INLINE_LEN> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_iter_unsized(iter)
+    }
+}
+
+impl<T: Clone, const INLINE_LEN: usize, A: Allocator + Clone> Clone
+    for SmallArrayBox<T, INLINE_LEN, A>
+{
     fn clone(&self) -> Self {
-        Self::new(self.iter().cloned())
+        Self::new_in(self.iter().cloned(), self.alloc.clone())
     }
 }
 
-impl<T, const INLINE_LEN: usize> SmallArrayBox<T, INLINE_LEN> {
-    pub(crate) fn uninit_inline_storage() -> Self {
+impl<T, const INLINE_LEN: usize, A: Allocator> SmallArrayBox<T, INLINE_LEN, A> {
+    const ASSERT_INLINE_LEN: () = assert!(
+        INLINE_LEN < HEAP_TAG as usize - 1,
+        "SmallArrayBox can store at most 126 elements inline"
+    );
+
+    const fn inline_tag(len: usize) -> NonZeroU8 {
+        match NonZeroU8::new(len as u8 + 1) {
+            Some(tag) => tag,
+            None => unreachable!(),
+        }
+    }
+
+    fn uninit_inline_storage_in(alloc: A) -> Self {
+        let () = Self::ASSERT_INLINE_LEN;
+
         Self {
+            _align: [],
             storage: SmallArrayBoxInner {
                 // Safety:
                 //
                 // It is safe because the array contains `MaybeUninit<T>`.
                 inline_storage: ManuallyDrop::new(unsafe { MaybeUninit::uninit().assume_init() }),
             },
-            len: 0,
+            tag: Self::inline_tag(0),
+            alloc,
         }
     }
 
-    pub const fn new_empty() -> Self {
+    /// * `ptr` - must be allocated by `alloc` with `Layout::array::<T>(len)`.
+    /// * `len` - must be larger than `INLINE_LEN`.
+    fn from_heap(ptr: NonNull<T>, len: usize, alloc: A) -> Self {
+        debug_assert!(len > INLINE_LEN);
+
+        let bytes = len.to_le_bytes();
+        let mut len_low = [0; LEN_LOW_BYTES];
+        len_low.copy_from_slice(&bytes[..LEN_LOW_BYTES]);
+
+        let len_high = bytes[LEN_LOW_BYTES];
+        assert!(
+            len_high < HEAP_TAG,
+            "SmallArrayBox cannot contain more than isize::MAX elements"
+        );
+
         Self {
+            _align: [],
             storage: SmallArrayBoxInner {
-                ptr: NonNull::dangling(),
+                heap: HeapRepr { ptr, len_low },
             },
-            len: 0,
+            tag: match NonZeroU8::new(HEAP_TAG | len_high) {
+                Some(tag) => tag,
+                None => unreachable!(),
+            },
+            alloc,
         }
     }
 
-    pub fn new<I>(iter: impl IntoIterator<IntoIter = I>) -> Self
+    #[inline(always)]
+    fn is_inline(&self) -> bool {
+        self.tag.get() < HEAP_TAG
+    }
+
+    #[inline(always)]
+    fn raw_len(&self) -> usize {
+        if self.is_inline() {
+            (self.tag.get() - 1) as usize
+        } else {
+            let HeapRepr { len_low, .. } = unsafe { self.storage.heap };
+
+            let mut bytes = [0; mem::size_of::<usize>()];
+            bytes[..LEN_LOW_BYTES].copy_from_slice(&len_low);
+            bytes[LEN_LOW_BYTES] = self.tag.get() & !HEAP_TAG;
+
+            usize::from_le_bytes(bytes)
+        }
+    }
+
+    #[inline(always)]
+    fn inline_ptr(&self) -> *const T {
+        &self.storage as *const _ as *const T
+    }
+
+    #[inline(always)]
+    fn inline_ptr_mut(&mut self) -> *mut T {
+        &mut self.storage as *mut _ as *mut T
+    }
+
+    #[inline(always)]
+    fn heap_ptr(&self) -> *mut T {
+        debug_assert!(!self.is_inline());
+
+        let HeapRepr { ptr, .. } = unsafe { self.storage.heap };
+        ptr.as_ptr()
+    }
+
+    #[inline(always)]
+    fn data_ptr(&self) -> *const T {
+        if self.is_inline() {
+            self.inline_ptr()
+        } else {
+            self.heap_ptr()
+        }
+    }
+
+    #[inline(always)]
+    fn data_ptr_mut(&mut self) -> *mut T {
+        if self.is_inline() {
+            self.inline_ptr_mut()
+        } else {
+            self.heap_ptr()
+        }
+    }
+
+    /// Free the heap storage without dropping the elements.
+    ///
+    /// # Safety
+    ///
+    /// The elements must have been moved out or dropped and `self` must not
+    /// be used afterwards except for dropping `self.alloc`.
+    unsafe fn dealloc_heap(&mut self) {
+        if !self.is_inline() {
+            let ptr = NonNull::new_unchecked(self.heap_ptr());
+            deallocate_array(&self.alloc, ptr, self.raw_len());
+        }
+    }
+
+    /// Move the allocator out without dropping the elements or freeing
+    /// the heap storage.
+    ///
+    /// # Safety
+    ///
+    /// The elements and the heap storage must have been moved out.
+    unsafe fn take_allocator(self) -> A {
+        let this = ManuallyDrop::new(self);
+        ptr::read(&this.alloc)
+    }
+
+    /// # Safety
+    ///
+    /// `self` must be stored inline.
+    pub(crate) unsafe fn inline_storage_mut(&mut self) -> &mut [MaybeUninit<T>; INLINE_LEN] {
+        debug_assert!(self.is_inline());
+
+        &mut *(self.inline_ptr_mut() as *mut [MaybeUninit<T>; INLINE_LEN])
+    }
+
+    /// # Safety
+    ///
+    /// `self` must be stored inline and the first `len` elements
+    /// must be initialized.
+    pub(crate) unsafe fn set_inline_len(&mut self, len: usize) {
+        debug_assert!(self.is_inline());
+        debug_assert!(len <= INLINE_LEN);
+
+        self.tag = Self::inline_tag(len);
+    }
+
+    pub const fn new_empty_in(alloc: A) -> Self {
+        let () = Self::ASSERT_INLINE_LEN;
+
+        Self {
+            _align: [],
+            storage: SmallArrayBoxInner {
+                heap: HeapRepr {
+                    ptr: NonNull::dangling(),
+                    len_low: [0; LEN_LOW_BYTES],
+                },
+            },
+            tag: Self::inline_tag(0),
+            alloc,
+        }
+    }
+
+    /// Same as `SmallArrayBox::new`, except that the heap storage is
+    /// allocated from `alloc`.
+    pub fn new_in<I>(iter: impl IntoIterator<IntoIter = I>, alloc: A) -> Self
     where
         I: Iterator<Item = T> + ExactSizeIterator,
     {
         let iter = iter.into_iter();
 
-        let len = iter.len();
+        if iter.len() <= INLINE_LEN {
+            Self::from_iter_inline_first(iter, alloc, Self::from_vec_in)
+        } else {
+            let len = iter.len();
+            Self::from_iter_with_len_in(iter, len, alloc)
+        }
+    }
+
+    /// Write the elements of `iter` straight into heap storage allocated
+    /// from `alloc` for `len` elements.
+    ///
+    /// The storage is grown or shrunk if `iter` yields more or fewer
+    /// elements, the elements are moved inline if they fit.
+    pub(crate) fn from_iter_with_len_in(
+        iter: impl IntoIterator<Item = T>,
+        len: usize,
+        alloc: A,
+    ) -> Self {
+        let mut guard = HeapGuard::with_capacity_in(len, alloc);
+        for elem in iter {
+            guard.push(elem);
+        }
+
+        if guard.len <= INLINE_LEN {
+            let (ptr, cap, len, alloc) = guard.into_raw_parts();
+            let mut this = Self::uninit_inline_storage_in(alloc);
+
+            unsafe {
+                ptr::copy_nonoverlapping(ptr.as_ptr(), this.inline_ptr_mut(), len);
+                this.set_inline_len(len);
+                deallocate_array(&this.alloc, ptr, cap);
+            }
+
+            return this;
+        }
+
+        if guard.len != guard.cap {
+            guard.resize(guard.len);
+        }
+
+        let (ptr, _cap, len, alloc) = guard.into_raw_parts();
+        Self::from_heap(ptr, len, alloc)
+    }
+
+    /// Same as `SmallArrayBox::from_iter_unsized`, except that the heap
+    /// storage is allocated from `alloc`.
+    pub fn from_iter_in(iter: impl IntoIterator<Item = T>, alloc: A) -> Self {
+        Self::from_iter_inline_first(iter.into_iter(), alloc, Self::from_vec_in)
+    }
+
+    /// Move elements of `vec` into storage allocated from `alloc`.
+    fn from_vec_in(mut vec: Vec<T>, alloc: A) -> Self {
+        let len = vec.len();
 
         if len <= INLINE_LEN {
-            let mut this = Self::uninit_inline_storage();
+            return Self::from_iter_inline_first(
+                vec.into_iter(),
+                alloc,
+                |_vec, _alloc| unreachable!(),
+            );
+        }
 
-            let inline_storage = unsafe { this.storage.inline_storage.deref_mut() };
+        let ptr = allocate_array(&alloc, len);
 
-            iter.zip(inline_storage).for_each(|(src, dst)| {
-                *dst = MaybeUninit::new(src);
-            });
+        unsafe {
+            ptr::copy_nonoverlapping(vec.as_ptr(), ptr.as_ptr(), len);
+            vec.set_len(0);
+        }
 
-            this.len = len;
+        Self::from_heap(ptr, len, alloc)
+    }
 
-            this
-        } else {
-            let vec: Vec<T> = iter.collect();
-            let array_ptr = Box::into_raw(vec.into_boxed_slice());
-            let slice = unsafe { &mut *array_ptr };
-            let ptr = unsafe { NonNull::new_unchecked(slice.as_mut_ptr()) };
+    /// * `spill` - called with all the elements once `iter` yields more than
+    ///   `INLINE_LEN` elements.
+    fn from_iter_inline_first(
+        mut iter: impl Iterator<Item = T>,
+        alloc: A,
+        spill: impl FnOnce(Vec<T>, A) -> Self,
+    ) -> Self {
+        // `this` also acts as the drop guard: if `iter` panics, the
+        // `len` elements written so far are dropped.
+        let mut this = Self::uninit_inline_storage_in(alloc);
+        let mut len = 0;
 
-            Self {
-                storage: SmallArrayBoxInner { ptr },
-                len,
+        while len < INLINE_LEN {
+            match iter.next() {
+                Some(elem) => unsafe {
+                    this.inline_storage_mut()[len] = MaybeUninit::new(elem);
+                    len += 1;
+                    this.set_inline_len(len);
+                },
+                None => return this,
             }
         }
+
+        match iter.next() {
+            None => this,
+            Some(elem) => {
+                let mut vec =
+                    Vec::with_capacity((INLINE_LEN + 1).saturating_add(iter.size_hint().0));
+
+                let alloc = unsafe {
+                    ptr::copy_nonoverlapping(this.inline_ptr(), vec.as_mut_ptr(), len);
+                    vec.set_len(len);
+                    this.set_inline_len(0);
+
+                    this.take_allocator()
+                };
+
+                vec.push(elem);
+                vec.extend(iter);
+                spill(vec, alloc)
+            }
+        }
+    }
+
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+}
+
+impl<T, const INLINE_LEN: usize> SmallArrayBox<T, INLINE_LEN> {
+    pub(crate) fn uninit_inline_storage() -> Self {
+        Self::uninit_inline_storage_in(Global)
+    }
+
+    pub const fn new_empty() -> Self {
+        Self::new_empty_in(Global)
+    }
+
+    /// `iter.len()` is only used to pick between inline and heap storage,
+    /// the elements are counted as they are written, so an iterator yielding
+    /// fewer or more elements than it claims is still handled correctly.
+    pub fn new<I>(iter: impl IntoIterator<IntoIter = I>) -> Self
+    where
+        I: Iterator<Item = T> + ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+
+        if iter.len() <= INLINE_LEN {
+            Self::from_iter_inline_first(iter, Global, |vec, _alloc| vec.into())
+        } else {
+            let len = iter.len();
+            Self::from_iter_with_len_in(iter, len, Global)
+        }
+    }
+
+    /// Unlike `SmallArrayBox::new`, `iter` does not need to know its length.
+    ///
+    /// Elements are collected inline first and moved to the heap once
+    /// `iter` yields more than `INLINE_LEN` elements.
+    pub fn from_iter_unsized(iter: impl IntoIterator<Item = T>) -> Self {
+        Self::from_iter_inline_first(iter.into_iter(), Global, |vec, _alloc| vec.into())
+    }
+
+    /// Same as `SmallArrayBox::from_iter_unsized`, except that it stops at
+    /// the first `Err` and returns it.
+    ///
+    /// Elements collected before the `Err` are dropped.
+    pub fn try_from_iter<E>(iter: impl IntoIterator<Item = Result<T, E>>) -> Result<Self, E> {
+        let iter = iter.into_iter();
+        let mut vec = SmallArrayVec::<T, INLINE_LEN>::with_capacity(iter.size_hint().0);
+
+        for elem in iter {
+            vec.push(elem?);
+        }
+
+        Ok(vec.into_small_array_box())
     }
 
     pub fn from_box(boxed: Box<[T]>) -> Self {
@@ -126,109 +578,335 @@ impl<T, const INLINE_LEN: usize> SmallArrayBox<T, INLINE_LEN> {
 
             debug_assert_eq!(slice.len(), len);
 
-            Self {
-                storage: SmallArrayBoxInner { ptr },
-                len,
-            }
+            // `Global` allocates from the same allocator as `Box`.
+            Self::from_heap(ptr, len, Global)
         }
     }
 
     pub fn into_boxed_slice(self) -> Box<[T]> {
-        let len = self.len;
+        let len = self.raw_len();
 
-        let mut this = ManuallyDrop::new(self);
+        let this = ManuallyDrop::new(self);
 
-        if len <= INLINE_LEN {
-            let inline_storage = unsafe { &mut this.storage.inline_storage };
+        if this.is_inline() {
             let mut vec = Vec::with_capacity(len);
 
-            for elem in inline_storage[..len].iter_mut() {
-                let ptr = elem.as_mut_ptr();
-                vec.push(unsafe { ptr.read() });
+            unsafe {
+                ptr::copy_nonoverlapping(this.inline_ptr(), vec.as_mut_ptr(), len);
+                vec.set_len(len);
             }
 
-            debug_assert_eq!(vec.len(), len);
-
             vec.into_boxed_slice()
         } else {
-            let ptr = unsafe { this.storage.ptr }.as_ptr();
-            let slice = unsafe { from_raw_parts_mut(ptr, len) };
+            let slice = unsafe { from_raw_parts_mut(this.heap_ptr(), len) };
             unsafe { Box::from_raw(slice) }
         }
     }
 }
 
-impl<T, const INLINE_LEN: usize> Deref for SmallArrayBox<T, INLINE_LEN> {
-    type Target = [T];
-
-    fn deref(&self) -> &Self::Target {
-        let len = self.len;
+#[cfg(feature = "allocator-api2")]
+impl<T, const INLINE_LEN: usize, A: Allocator> SmallArrayBox<T, INLINE_LEN, A> {
+    /// Take over the heap storage and the allocator of `boxed` if it cannot
+    /// be stored inline.
+    pub fn from_box_in(boxed: allocator_api2::boxed::Box<[T], A>) -> Self {
+        let len = boxed.len();
+        let (array_ptr, alloc) = allocator_api2::boxed::Box::into_raw_with_allocator(boxed);
+        let ptr = unsafe { NonNull::new_unchecked(array_ptr as *mut T) };
 
         if len <= INLINE_LEN {
-            let inline_storage = unsafe { self.storage.inline_storage.deref() };
-            unsafe { &*(&inline_storage[..len] as *const _ as *const [T]) }
+            let mut this = Self::uninit_inline_storage_in(alloc);
+
+            unsafe {
+                ptr::copy_nonoverlapping(ptr.as_ptr(), this.inline_ptr_mut(), len);
+                this.set_inline_len(len);
+                deallocate_array(&this.alloc, ptr, len);
+            }
+
+            this
         } else {
-            let ptr = unsafe { self.storage.ptr }.as_ptr();
-            unsafe { from_raw_parts(ptr, len) }
+            Self::from_heap(ptr, len, alloc)
+        }
+    }
+
+    /// Convert into a `Box` that keeps the allocator of `self`.
+    pub fn into_boxed_slice_in(self) -> allocator_api2::boxed::Box<[T], A> {
+        let len = self.raw_len();
+
+        unsafe {
+            let ptr = if self.is_inline() {
+                let ptr = allocate_array(&self.alloc, len);
+                ptr::copy_nonoverlapping(self.inline_ptr(), ptr.as_ptr(), len);
+                ptr.as_ptr()
+            } else {
+                self.heap_ptr()
+            };
+
+            let alloc = self.take_allocator();
+            allocator_api2::boxed::Box::from_raw_in(ptr::slice_from_raw_parts_mut(ptr, len), alloc)
         }
     }
 }
 
-impl<T, const INLINE_LEN: usize> DerefMut for SmallArrayBox<T, INLINE_LEN> {
+impl<T, const INLINE_LEN: usize, A: Allocator> Deref for SmallArrayBox<T, INLINE_LEN, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { from_raw_parts(self.data_ptr(), self.raw_len()) }
+    }
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> DerefMut for SmallArrayBox<T, INLINE_LEN, A> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        let len = self.len;
+        unsafe { from_raw_parts_mut(self.data_ptr_mut(), self.raw_len()) }
+    }
+}
 
-        if len <= INLINE_LEN {
-            let inline_storage = unsafe { self.storage.inline_storage.deref_mut() };
-            unsafe { &mut *(&mut inline_storage[..len] as *mut _ as *mut [T]) }
-        } else {
-            let ptr = unsafe { self.storage.ptr }.as_ptr();
-            unsafe { from_raw_parts_mut(ptr, len) }
+impl<T, const INLINE_LEN: usize, A: Allocator> Drop for SmallArrayBox<T, INLINE_LEN, A> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.deref_mut());
+            self.dealloc_heap();
         }
     }
 }
 
-impl<T, const INLINE_LEN: usize> Drop for SmallArrayBox<T, INLINE_LEN> {
-    fn drop(&mut self) {
-        let len = self.len;
+impl<T, const INLINE_LEN: usize, A: Allocator> IntoIterator for SmallArrayBox<T, INLINE_LEN, A> {
+    type Item = T;
+    type IntoIter = SmallArrayBoxIntoIter<T, INLINE_LEN, A>;
 
-        if len <= INLINE_LEN {
-            let inline_storage = unsafe { self.storage.inline_storage.deref_mut() };
+    fn into_iter(self) -> Self::IntoIter {
+        let end = self.raw_len();
 
-            inline_storage[..len].iter_mut().for_each(|elem| {
-                let ptr = elem.as_mut_ptr();
-                unsafe {
-                    ptr.drop_in_place();
-                }
-            });
+        SmallArrayBoxIntoIter {
+            array: ManuallyDrop::new(self),
+            start: 0,
+            end,
+        }
+    }
+}
+
+impl<'a, T, const INLINE_LEN: usize, A: Allocator> IntoIterator
+    for &'a SmallArrayBox<T, INLINE_LEN, A>
+{
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const INLINE_LEN: usize, A: Allocator> IntoIterator
+    for &'a mut SmallArrayBox<T, INLINE_LEN, A>
+{
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// By-value iterator of `SmallArrayBox`, created by `SmallArrayBox::into_iter`.
+pub struct SmallArrayBoxIntoIter<T, const INLINE_LEN: usize, A: Allocator = Global> {
+    /// Elements in `array[start..end]` have not been yielded yet,
+    /// the rest has been moved out.
+    array: ManuallyDrop<SmallArrayBox<T, INLINE_LEN, A>>,
+    start: usize,
+    end: usize,
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> SmallArrayBoxIntoIter<T, INLINE_LEN, A> {
+    /// Return the remaining elements.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { from_raw_parts(self.array.data_ptr().add(self.start), self.len()) }
+    }
+
+    /// Return the remaining elements.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        let len = self.len();
+        unsafe { from_raw_parts_mut(self.array.data_ptr_mut().add(self.start), len) }
+    }
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> Iterator
+    for SmallArrayBoxIntoIter<T, INLINE_LEN, A>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start == self.end {
+            None
         } else {
-            let ptr = unsafe { self.storage.ptr }.as_ptr();
-            let slice = unsafe { from_raw_parts_mut(ptr, len) };
-            drop(unsafe { Box::from_raw(slice) });
+            let elem = unsafe { self.array.data_ptr().add(self.start).read() };
+            self.start += 1;
+            Some(elem)
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
 }
 
-impl<T: Debug, const INLINE_LEN: usize> Debug for SmallArrayBox<T, INLINE_LEN> {
+impl<T, const INLINE_LEN: usize, A: Allocator> DoubleEndedIterator
+    for SmallArrayBoxIntoIter<T, INLINE_LEN, A>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.start == self.end {
+            None
+        } else {
+            self.end -= 1;
+            Some(unsafe { self.array.data_ptr().add(self.end).read() })
+        }
+    }
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> ExactSizeIterator
+    for SmallArrayBoxIntoIter<T, INLINE_LEN, A>
+{
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> FusedIterator
+    for SmallArrayBoxIntoIter<T, INLINE_LEN, A>
+{
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> Drop for SmallArrayBoxIntoIter<T, INLINE_LEN, A> {
+    fn drop(&mut self) {
+        let remaining = self.as_mut_slice() as *mut [T];
+        self.start = self.end;
+
+        unsafe {
+            ptr::drop_in_place(remaining);
+            self.array.dealloc_heap();
+            ptr::drop_in_place(&mut self.array.alloc);
+        }
+    }
+}
+
+impl<T: Clone, const INLINE_LEN: usize, A: Allocator + Clone> Clone
+    for SmallArrayBoxIntoIter<T, INLINE_LEN, A>
+{
+    fn clone(&self) -> Self {
+        SmallArrayBox::new_in(self.as_slice().iter().cloned(), self.array.alloc.clone()).into_iter()
+    }
+}
+
+impl<T: Debug, const INLINE_LEN: usize, A: Allocator> Debug
+    for SmallArrayBoxIntoIter<T, INLINE_LEN, A>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SmallArrayBoxIntoIter")
+            .field(&self.as_slice())
+            .finish()
+    }
+}
+
+impl<T: Debug, const INLINE_LEN: usize, A: Allocator> Debug for SmallArrayBox<T, INLINE_LEN, A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:#?}", self.deref())
     }
 }
 
-impl<T: PartialEq, const INLINE_LEN: usize> PartialEq for SmallArrayBox<T, INLINE_LEN> {
+impl<T: PartialEq, const INLINE_LEN: usize, A: Allocator> PartialEq
+    for SmallArrayBox<T, INLINE_LEN, A>
+{
     fn eq(&self, other: &Self) -> bool {
         self.deref().eq(other.deref())
     }
 }
 
-impl<T: Eq, const INLINE_LEN: usize> Eq for SmallArrayBox<T, INLINE_LEN> {}
+impl<T: Eq, const INLINE_LEN: usize, A: Allocator> Eq for SmallArrayBox<T, INLINE_LEN, A> {}
+
+impl<T, U, const INLINE_LEN: usize, A: Allocator> PartialEq<[U]> for SmallArrayBox<T, INLINE_LEN, A>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &[U]) -> bool {
+        self.deref().eq(other)
+    }
+}
+
+impl<T, U, const INLINE_LEN: usize, A: Allocator> PartialEq<&[U]>
+    for SmallArrayBox<T, INLINE_LEN, A>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &&[U]) -> bool {
+        self.deref().eq(*other)
+    }
+}
+
+impl<T, U, const INLINE_LEN: usize, A: Allocator> PartialEq<Vec<U>>
+    for SmallArrayBox<T, INLINE_LEN, A>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &Vec<U>) -> bool {
+        self.deref().eq(other.as_slice())
+    }
+}
+
+impl<T: PartialOrd, const INLINE_LEN: usize, A: Allocator> PartialOrd
+    for SmallArrayBox<T, INLINE_LEN, A>
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.deref().partial_cmp(other.deref())
+    }
+}
+
+impl<T: Ord, const INLINE_LEN: usize, A: Allocator> Ord for SmallArrayBox<T, INLINE_LEN, A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deref().cmp(other.deref())
+    }
+}
+
+/// Same as the hash of `[T]`, so that `Borrow<[T]>` can be used to look up
+/// `SmallArrayBox` in `HashMap` and `HashSet`.
+impl<T: Hash, const INLINE_LEN: usize, A: Allocator> Hash for SmallArrayBox<T, INLINE_LEN, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.deref().hash(state)
+    }
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> AsRef<[T]> for SmallArrayBox<T, INLINE_LEN, A> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> AsMut<[T]> for SmallArrayBox<T, INLINE_LEN, A> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> Borrow<[T]> for SmallArrayBox<T, INLINE_LEN, A> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, const INLINE_LEN: usize, A: Allocator> BorrowMut<[T]> for SmallArrayBox<T, INLINE_LEN, A> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
 
 #[cfg(test)]
 mod tests {
     type SmallArrayBox = super::SmallArrayBox<u8, 8>;
 
+    use std::collections::HashMap;
     use std::ops::{Deref, DerefMut};
+    use std::panic::{self, AssertUnwindSafe};
     use std::ptr;
+    use std::rc::Rc;
 
     fn assert_ptr_eq(x: *const [u8], y: *const [u8]) {
         assert!(ptr::eq(x, y));
@@ -288,4 +966,362 @@ mod tests {
             assert_eq!(&*boxed, slice);
         }
     }
+
+    #[test]
+    fn test_into_iter() {
+        let vec: Vec<String> = (0..100).map(|n| n.to_string()).collect();
+
+        for len in 0..vec.len() {
+            let slice = &vec[..len];
+
+            let array = super::SmallArrayBox::<String, 8>::from(slice);
+            let iter = array.into_iter();
+            assert_eq!(iter.len(), len);
+            assert_eq!(iter.as_slice(), slice);
+            assert!(iter.eq(slice.iter().cloned()));
+
+            let array = super::SmallArrayBox::<String, 8>::from(slice);
+            assert!(array.into_iter().rev().eq(slice.iter().rev().cloned()));
+        }
+
+        let mut array = SmallArrayBox::new(0..8);
+        for elem in &mut array {
+            *elem += 1;
+        }
+        assert!((&array).into_iter().copied().eq(1..9));
+    }
+
+    #[test]
+    fn test_into_iter_partial() {
+        let rc = Rc::new(());
+
+        for len in [0, 3, 8, 9, 20] {
+            let array = super::SmallArrayBox::<Rc<()>, 8>::new((0..len).map(|_| rc.clone()));
+            assert_eq!(Rc::strong_count(&rc), len + 1);
+
+            let mut iter = array.into_iter();
+
+            if len > 0 {
+                drop(iter.next());
+                drop(iter.next_back());
+                assert_eq!(iter.len(), len.saturating_sub(2));
+                assert_eq!(Rc::strong_count(&rc), len.saturating_sub(2) + 1);
+            }
+
+            let cloned = iter.clone();
+            drop(iter);
+            assert_eq!(cloned.len(), len.saturating_sub(2));
+            drop(cloned);
+
+            assert_eq!(Rc::strong_count(&rc), 1);
+        }
+    }
+
+    #[test]
+    fn test_from_iter_unsized() {
+        let vec: Vec<u8> = (0..100).collect();
+
+        for len in 0..vec.len() {
+            let slice = &vec[..len];
+
+            let array: SmallArrayBox = (0..200).filter(|n| *n < len as u8).collect();
+            assert_eq!(array.deref(), slice);
+
+            let array = SmallArrayBox::from_iter_unsized(
+                slice.chunks(3).flat_map(|chunk| chunk.iter().copied()),
+            );
+            assert_eq!(array.deref(), slice);
+        }
+    }
+
+    #[test]
+    fn test_try_from_iter() {
+        let rc = Rc::new(());
+
+        for len in [0, 3, 8, 9, 20] {
+            let array = super::SmallArrayBox::<Rc<()>, 8>::try_from_iter(
+                (0..len).map(|_| Ok::<_, ()>(rc.clone())),
+            )
+            .unwrap();
+            assert_eq!(array.len(), len);
+            drop(array);
+
+            let res = super::SmallArrayBox::<Rc<()>, 8>::try_from_iter((0..(len + 1)).map(|i| {
+                if i < len {
+                    Ok(rc.clone())
+                } else {
+                    Err(i)
+                }
+            }));
+            assert_eq!(res.unwrap_err(), len);
+
+            assert_eq!(Rc::strong_count(&rc), 1);
+        }
+    }
+
+    #[test]
+    fn test_hash_borrow() {
+        let mut map = HashMap::new();
+
+        for len in 0..20 {
+            let vec: Vec<u8> = (0..len).collect();
+            map.insert(SmallArrayBox::from(vec), len);
+        }
+
+        for len in 0..20 {
+            let vec: Vec<u8> = (0..len).collect();
+            assert_eq!(map.get(vec.as_slice()), Some(&len));
+        }
+    }
+
+    #[test]
+    fn test_ord() {
+        let mut arrays: Vec<SmallArrayBox> = vec![
+            SmallArrayBox::from([2, 1]),
+            SmallArrayBox::from([0; 10]),
+            SmallArrayBox::from([]),
+            SmallArrayBox::from([2]),
+            SmallArrayBox::from([1; 9]),
+        ];
+        arrays.sort();
+
+        let sorted: [&[u8]; 5] = [&[], &[0; 10], &[1; 9], &[2], &[2, 1]];
+        assert!(arrays.iter().eq(sorted.iter()));
+        assert!(arrays[0] < arrays[1]);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let vec: Vec<u8> = (0..100).collect();
+
+        for len in 0..vec.len() {
+            let slice = &vec[..len];
+
+            let array = SmallArrayBox::from(slice);
+            assert_eq!(array, *slice);
+            assert_eq!(array, slice);
+            assert_eq!(array, slice.to_vec());
+            assert_eq!(array.as_ref(), slice);
+            assert_eq!(Vec::from(array.clone()), slice);
+            assert_eq!(&*Box::<[u8]>::from(array), slice);
+        }
+
+        assert_eq!(SmallArrayBox::from([1, 2, 3]), [1, 2, 3][..]);
+        assert_eq!(SmallArrayBox::from([7; 20]), [7; 20][..]);
+    }
+
+    /// `ExactSizeIterator` that claims `claimed` elements but yields
+    /// `actual` elements, panicking instead of yielding the `panic_at`-th one.
+    struct LyingIter {
+        rc: Rc<()>,
+        claimed: usize,
+        actual: usize,
+        panic_at: Option<usize>,
+        yielded: usize,
+    }
+
+    impl LyingIter {
+        fn new(rc: &Rc<()>, claimed: usize, actual: usize) -> Self {
+            Self {
+                rc: rc.clone(),
+                claimed,
+                actual,
+                panic_at: None,
+                yielded: 0,
+            }
+        }
+    }
+
+    impl Iterator for LyingIter {
+        type Item = Rc<()>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if Some(self.yielded) == self.panic_at {
+                panic!("LyingIter panics");
+            }
+
+            if self.yielded < self.actual {
+                self.yielded += 1;
+                Some(self.rc.clone())
+            } else {
+                None
+            }
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.claimed, Some(self.claimed))
+        }
+    }
+
+    impl ExactSizeIterator for LyingIter {}
+
+    #[test]
+    fn test_new_lying_iter() {
+        let rc = Rc::new(());
+
+        for claimed in [0, 3, 8, 9, 20] {
+            for actual in [0, 2, 7, 8, 9, 30] {
+                let array =
+                    super::SmallArrayBox::<Rc<()>, 8>::new(LyingIter::new(&rc, claimed, actual));
+
+                assert_eq!(array.len(), actual);
+                assert!(array.iter().all(|elem| Rc::ptr_eq(elem, &rc)));
+                assert_eq!(Rc::strong_count(&rc), actual + 1);
+
+                drop(array);
+                assert_eq!(Rc::strong_count(&rc), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_new_panicking_iter() {
+        let rc = Rc::new(());
+
+        for claimed in [3, 8, 9, 20] {
+            for panic_at in [0, 2, 8, 10] {
+                let mut iter = LyingIter::new(&rc, claimed, 30);
+                iter.panic_at = Some(panic_at);
+
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    super::SmallArrayBox::<Rc<()>, 8>::new(iter)
+                }));
+                assert!(res.is_err());
+
+                assert_eq!(Rc::strong_count(&rc), 1);
+            }
+        }
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn test_new_in_lying_iter() {
+        use crate::allocator::tests::CountingAllocator;
+
+        let alloc = CountingAllocator::default();
+        let rc = Rc::new(());
+
+        for claimed in [9, 20] {
+            for actual in [0, 8, 9, 30] {
+                let array = super::SmallArrayBox::<Rc<()>, 8, _>::new_in(
+                    LyingIter::new(&rc, claimed, actual),
+                    &alloc,
+                );
+
+                assert_eq!(array.len(), actual);
+                assert_eq!(Rc::strong_count(&rc), actual + 1);
+                assert_eq!(alloc.live(), (actual > 8) as usize);
+
+                drop(array);
+                assert_eq!(Rc::strong_count(&rc), 1);
+                assert_eq!(alloc.live(), 0);
+            }
+
+            for panic_at in [0, 10, 25] {
+                let mut iter = LyingIter::new(&rc, claimed, 30);
+                iter.panic_at = Some(panic_at);
+
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    super::SmallArrayBox::<Rc<()>, 8, _>::new_in(iter, &alloc)
+                }));
+                assert!(res.is_err());
+
+                assert_eq!(Rc::strong_count(&rc), 1);
+                assert_eq!(alloc.live(), 0);
+            }
+        }
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn test_allocator() {
+        use crate::allocator::tests::CountingAllocator;
+
+        let alloc = CountingAllocator::default();
+        let vec: Vec<String> = (0..20).map(|n| n.to_string()).collect();
+
+        for len in 0..vec.len() {
+            let slice = &vec[..len];
+            let spilled = (len > 8) as usize;
+
+            let array = super::SmallArrayBox::<String, 8, _>::new_in(slice.iter().cloned(), &alloc);
+            assert_eq!(array.deref(), slice);
+            assert_eq!(alloc.live(), spilled);
+
+            let cloned = array.clone();
+            assert_eq!(cloned, array);
+            assert_eq!(alloc.live(), spilled * 2);
+
+            let mut iter = array.into_iter();
+            assert_eq!(iter.next(), slice.first().cloned());
+            drop(iter);
+            assert_eq!(alloc.live(), spilled);
+
+            let boxed = cloned.into_boxed_slice_in();
+            assert_eq!(&*boxed, slice);
+            assert_eq!(alloc.live(), 1);
+
+            let array = super::SmallArrayBox::<String, 8, _>::from_box_in(boxed);
+            assert_eq!(array.deref(), slice);
+            assert_eq!(alloc.live(), spilled);
+            drop(array);
+
+            let array = super::SmallArrayBox::<String, 8, _>::from_iter_in(
+                vec.iter().filter(|s| s.len() == 1).take(len).cloned(),
+                &alloc,
+            );
+            assert_eq!(array.len(), len.min(10));
+            assert_eq!(alloc.live(), (len.min(10) > 8) as usize);
+            drop(array);
+
+            assert_eq!(alloc.live(), 0);
+        }
+    }
+
+    #[test]
+    fn test_size() {
+        use std::mem::size_of;
+
+        let ptr_size = size_of::<usize>();
+
+        assert_eq!(size_of::<super::SmallArrayBox<u8, 15>>(), ptr_size * 2);
+        assert_eq!(
+            size_of::<Option<super::SmallArrayBox<u8, 15>>>(),
+            ptr_size * 2
+        );
+        assert_eq!(size_of::<SmallArrayBox>(), ptr_size * 2);
+        assert_eq!(size_of::<Option<SmallArrayBox>>(), ptr_size * 2);
+        assert_eq!(size_of::<super::SmallArrayBox<u64, 1>>(), ptr_size * 2);
+        assert_eq!(
+            size_of::<Option<super::SmallArrayBox<String, 1>>>(),
+            size_of::<String>() + ptr_size
+        );
+    }
+
+    #[test]
+    fn test_inline_len_limit() {
+        let vec: Vec<u8> = (0..=255).collect();
+
+        for len in 0..vec.len() {
+            let slice = &vec[..len];
+
+            let array = super::SmallArrayBox::<u8, 126>::from(slice);
+            assert_eq!(array.deref(), slice);
+            assert_eq!(&*array.into_boxed_slice(), slice);
+        }
+    }
+
+    #[test]
+    fn test_zst_heap_len_limit() {
+        let max = isize::MAX as usize;
+
+        let array = super::SmallArrayBox::<(), 1>::from(vec![(); max]);
+        assert_eq!(array.len(), max);
+    }
+
+    #[test]
+    #[should_panic(expected = "more than isize::MAX elements")]
+    fn test_zst_heap_len_overflow() {
+        let _array = super::SmallArrayBox::<(), 1>::from(vec![(); isize::MAX as usize + 1]);
+    }
 }
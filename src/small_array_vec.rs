@@ -0,0 +1,637 @@
+use super::SmallArrayBox;
+
+use alloc::vec::Vec;
+
+use core::cmp;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ptr::{self, NonNull};
+use core::slice::{self, from_raw_parts, from_raw_parts_mut};
+
+use core::iter::{Extend, FromIterator, IntoIterator, Iterator};
+
+use core::fmt::{self, Debug};
+use core::ops::{Deref, DerefMut};
+
+use core::cmp::{Eq, PartialEq};
+
+union SmallArrayVecInner<T, const INLINE_LEN: usize> {
+    ptr: NonNull<T>,
+    inline_storage: ManuallyDrop<[MaybeUninit<T>; INLINE_LEN]>,
+}
+
+/// Growable version of `SmallArrayBox`.
+///
+/// * `INLINE_LEN` - Number of elements that can be stored inline,
+///   it spills to the heap once it needs more than that.
+pub struct SmallArrayVec<T, const INLINE_LEN: usize> {
+    storage: SmallArrayVecInner<T, INLINE_LEN>,
+    len: usize,
+    /// `capacity > INLINE_LEN` if and only if spilled to the heap.
+    capacity: usize,
+}
+
+unsafe impl<T: Send, const INLINE_LEN: usize> Send for SmallArrayVec<T, INLINE_LEN> {}
+unsafe impl<T: Sync, const INLINE_LEN: usize> Sync for SmallArrayVec<T, INLINE_LEN> {}
+
+impl<T, const INLINE_LEN: usize> Default for SmallArrayVec<T, INLINE_LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const INLINE_LEN: usize> SmallArrayVec<T, INLINE_LEN> {
+    pub const fn new() -> Self {
+        Self {
+            storage: SmallArrayVecInner {
+                ptr: NonNull::dangling(),
+            },
+            len: 0,
+            capacity: INLINE_LEN,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        if capacity <= INLINE_LEN {
+            Self::new()
+        } else {
+            Vec::with_capacity(capacity).into()
+        }
+    }
+
+    fn uninit_inline_storage() -> ManuallyDrop<[MaybeUninit<T>; INLINE_LEN]> {
+        // Safety:
+        //
+        // It is safe because the array contains `MaybeUninit<T>`.
+        ManuallyDrop::new(unsafe { MaybeUninit::uninit().assume_init() })
+    }
+
+    /// Return `true` if the elements are stored on the heap.
+    #[inline(always)]
+    pub fn spilled(&self) -> bool {
+        self.capacity > INLINE_LEN
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn as_ptr(&self) -> *const T {
+        if self.spilled() {
+            unsafe { self.storage.ptr }.as_ptr()
+        } else {
+            unsafe { self.storage.inline_storage.as_ptr() as *const T }
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        if self.spilled() {
+            unsafe { self.storage.ptr }.as_ptr()
+        } else {
+            unsafe { self.storage.inline_storage.deref_mut().as_mut_ptr() as *mut T }
+        }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { from_raw_parts(self.as_ptr(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+
+    /// # Safety
+    ///
+    /// `self` must be spilled and the returned `Vec` must be put back
+    /// using `set_heap_vec` once done.
+    unsafe fn heap_vec(&mut self) -> ManuallyDrop<Vec<T>> {
+        debug_assert!(self.spilled());
+
+        ManuallyDrop::new(Vec::from_raw_parts(
+            self.storage.ptr.as_ptr(),
+            self.len,
+            self.capacity,
+        ))
+    }
+
+    /// * `vec` - must have capacity larger than `INLINE_LEN`.
+    fn set_heap_vec(&mut self, mut vec: ManuallyDrop<Vec<T>>) {
+        debug_assert!(vec.capacity() > INLINE_LEN);
+
+        self.storage.ptr = unsafe { NonNull::new_unchecked(vec.as_mut_ptr()) };
+        self.len = vec.len();
+        self.capacity = vec.capacity();
+    }
+
+    /// Move inline elements to a newly allocated buffer of `capacity`.
+    ///
+    /// * `capacity` - must be larger than `INLINE_LEN`.
+    fn spill(&mut self, capacity: usize) {
+        debug_assert!(!self.spilled());
+        debug_assert!(capacity > INLINE_LEN);
+
+        let mut vec = ManuallyDrop::new(Vec::with_capacity(capacity));
+
+        unsafe {
+            ptr::copy_nonoverlapping(self.as_ptr(), vec.as_mut_ptr(), self.len);
+            vec.set_len(self.len);
+        }
+
+        self.set_heap_vec(vec);
+    }
+
+    /// Reserve capacity for at least `additional` more elements.
+    pub fn reserve(&mut self, additional: usize) {
+        if self.spilled() {
+            let mut vec = unsafe { self.heap_vec() };
+            vec.reserve(additional);
+            self.set_heap_vec(vec);
+        } else if additional > INLINE_LEN - self.len {
+            let required = self.len.checked_add(additional).expect("capacity overflow");
+            self.spill(cmp::max(required, INLINE_LEN * 2));
+        }
+    }
+
+    /// Reserve capacity for exactly `additional` more elements.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if self.spilled() {
+            let mut vec = unsafe { self.heap_vec() };
+            vec.reserve_exact(additional);
+            self.set_heap_vec(vec);
+        } else if additional > INLINE_LEN - self.len {
+            self.spill(self.len.checked_add(additional).expect("capacity overflow"));
+        }
+    }
+
+    /// Shrink the capacity as much as possible, moving the elements back
+    /// inline if they fit.
+    pub fn shrink_to_fit(&mut self) {
+        if !self.spilled() {
+            return;
+        }
+
+        let len = self.len;
+
+        if len <= INLINE_LEN {
+            let heap_ptr = unsafe { self.storage.ptr }.as_ptr();
+            let capacity = self.capacity;
+
+            let mut inline_storage = Self::uninit_inline_storage();
+            unsafe {
+                ptr::copy_nonoverlapping(heap_ptr, inline_storage.as_mut_ptr() as *mut T, len);
+            }
+
+            self.storage.inline_storage = inline_storage;
+            self.capacity = INLINE_LEN;
+
+            drop(unsafe { Vec::from_raw_parts(heap_ptr, 0, capacity) });
+        } else {
+            let mut vec = unsafe { self.heap_vec() };
+            vec.shrink_to_fit();
+            self.set_heap_vec(vec);
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len == self.capacity {
+            self.reserve(1);
+        }
+
+        unsafe { self.as_mut_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { self.as_ptr().add(self.len).read() })
+        }
+    }
+
+    /// Insert `value` at `index`, shifting all elements after it to the right.
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, value: T) {
+        let len = self.len;
+
+        assert!(
+            index <= len,
+            "insertion index (is {}) should be <= len (is {})",
+            index,
+            len
+        );
+
+        if len == self.capacity {
+            self.reserve(1);
+        }
+
+        unsafe {
+            let p = self.as_mut_ptr().add(index);
+            ptr::copy(p, p.add(1), len - index);
+            p.write(value);
+        }
+        self.len += 1;
+    }
+
+    /// Remove and return the element at `index`, shifting all elements
+    /// after it to the left.
+    ///
+    /// Panics if `index >= len`.
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len;
+
+        assert!(
+            index < len,
+            "removal index (is {}) should be < len (is {})",
+            index,
+            len
+        );
+
+        unsafe {
+            let p = self.as_mut_ptr().add(index);
+            let value = p.read();
+            ptr::copy(p.add(1), p, len - index - 1);
+            self.len -= 1;
+            value
+        }
+    }
+
+    /// Shorten to `len` elements and drop the rest.
+    ///
+    /// Does nothing if `len` is greater than or equal to the current length.
+    pub fn truncate(&mut self, len: usize) {
+        let old_len = self.len;
+
+        if len >= old_len {
+            return;
+        }
+
+        self.len = len;
+
+        unsafe {
+            let tail = from_raw_parts_mut(self.as_mut_ptr().add(len), old_len - len);
+            ptr::drop_in_place(tail);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        let mut this = ManuallyDrop::new(self);
+
+        if this.spilled() {
+            ManuallyDrop::into_inner(unsafe { this.heap_vec() })
+        } else {
+            let len = this.len;
+            let mut vec = Vec::with_capacity(len);
+
+            unsafe {
+                ptr::copy_nonoverlapping(this.as_ptr(), vec.as_mut_ptr(), len);
+                vec.set_len(len);
+            }
+
+            vec
+        }
+    }
+
+    /// Shrink and convert into `SmallArrayBox`, the elements are moved
+    /// back inline if they fit.
+    pub fn into_small_array_box(self) -> SmallArrayBox<T, INLINE_LEN> {
+        if self.spilled() {
+            self.into_vec().into()
+        } else {
+            let this = ManuallyDrop::new(self);
+            let len = this.len;
+
+            let mut array = SmallArrayBox::uninit_inline_storage();
+
+            unsafe {
+                let dst = array.inline_storage_mut().as_mut_ptr() as *mut T;
+                ptr::copy_nonoverlapping(this.as_ptr(), dst, len);
+                array.set_inline_len(len);
+            }
+
+            array
+        }
+    }
+}
+
+impl<T, const INLINE_LEN: usize> From<Vec<T>> for SmallArrayVec<T, INLINE_LEN> {
+    fn from(vec: Vec<T>) -> Self {
+        if vec.capacity() <= INLINE_LEN {
+            let mut this = Self::new();
+            let mut vec = ManuallyDrop::new(vec);
+            let len = vec.len();
+
+            unsafe {
+                let dst = this.storage.inline_storage.deref_mut().as_mut_ptr() as *mut T;
+                ptr::copy_nonoverlapping(vec.as_ptr(), dst, len);
+                vec.set_len(0);
+                ManuallyDrop::drop(&mut vec);
+            }
+            this.len = len;
+
+            this
+        } else {
+            let mut this = Self::new();
+            this.set_heap_vec(ManuallyDrop::new(vec));
+            this
+        }
+    }
+}
+
+impl<T, const INLINE_LEN: usize> From<SmallArrayVec<T, INLINE_LEN>> for Vec<T> {
+    fn from(vec: SmallArrayVec<T, INLINE_LEN>) -> Self {
+        vec.into_vec()
+    }
+}
+
+impl<T, const INLINE_LEN: usize> From<SmallArrayBox<T, INLINE_LEN>>
+    for SmallArrayVec<T, INLINE_LEN>
+{
+    fn from(array: SmallArrayBox<T, INLINE_LEN>) -> Self {
+        let len = array.len();
+
+        if len <= INLINE_LEN {
+            let array = ManuallyDrop::new(array);
+            let mut this = Self::new();
+
+            unsafe {
+                let dst = this.storage.inline_storage.deref_mut().as_mut_ptr() as *mut T;
+                ptr::copy_nonoverlapping(array.as_ptr(), dst, len);
+            }
+            this.len = len;
+
+            this
+        } else {
+            array.into_boxed_slice().into_vec().into()
+        }
+    }
+}
+
+impl<T, const INLINE_LEN: usize> From<SmallArrayVec<T, INLINE_LEN>>
+    for SmallArrayBox<T, INLINE_LEN>
+{
+    fn from(vec: SmallArrayVec<T, INLINE_LEN>) -> Self {
+        vec.into_small_array_box()
+    }
+}
+
+impl<T: Clone, const INLINE_LEN: usize> From<&[T]> for SmallArrayVec<T, INLINE_LEN> {
+    fn from(slice: &[T]) -> Self {
+        slice.iter().cloned().collect()
+    }
+}
+
+impl<T: Clone, const INLINE_LEN: usize> Clone for SmallArrayVec<T, INLINE_LEN> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T, const INLINE_LEN: usize> Extend<T> for SmallArrayVec<T, INLINE_LEN> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T, const INLINE_LEN: usize> FromIterator<T> for SmallArrayVec<T, INLINE_LEN> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut this = Self::new();
+        this.extend(iter);
+        this
+    }
+}
+
+impl<'a, T, const INLINE_LEN: usize> IntoIterator for &'a SmallArrayVec<T, INLINE_LEN> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const INLINE_LEN: usize> IntoIterator for &'a mut SmallArrayVec<T, INLINE_LEN> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T, const INLINE_LEN: usize> Deref for SmallArrayVec<T, INLINE_LEN> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T, const INLINE_LEN: usize> DerefMut for SmallArrayVec<T, INLINE_LEN> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, const INLINE_LEN: usize> Drop for SmallArrayVec<T, INLINE_LEN> {
+    fn drop(&mut self) {
+        if self.spilled() {
+            drop(ManuallyDrop::into_inner(unsafe { self.heap_vec() }));
+        } else {
+            unsafe { ptr::drop_in_place(self.as_mut_slice()) };
+        }
+    }
+}
+
+impl<T: Debug, const INLINE_LEN: usize> Debug for SmallArrayVec<T, INLINE_LEN> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#?}", self.deref())
+    }
+}
+
+impl<T: PartialEq, const INLINE_LEN: usize> PartialEq for SmallArrayVec<T, INLINE_LEN> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deref().eq(other.deref())
+    }
+}
+
+impl<T: Eq, const INLINE_LEN: usize> Eq for SmallArrayVec<T, INLINE_LEN> {}
+
+#[cfg(test)]
+mod tests {
+    const INLINE_LEN: usize = 8;
+
+    type SmallArrayVec<T> = super::SmallArrayVec<T, INLINE_LEN>;
+
+    use std::ops::Deref;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_push_pop() {
+        let mut vec = SmallArrayVec::new();
+        let input: Vec<u8> = (0..100).collect();
+
+        assert!(vec.is_empty());
+        assert!(!vec.spilled());
+        assert_eq!(vec.capacity(), INLINE_LEN);
+
+        for (i, n) in input.iter().copied().enumerate() {
+            vec.push(n);
+
+            assert_eq!(vec.len(), i + 1);
+            assert_eq!(vec.spilled(), i >= INLINE_LEN);
+            assert_eq!(vec.deref(), &input[..=i]);
+        }
+
+        for i in (0..input.len()).rev() {
+            assert_eq!(vec.pop(), Some(input[i]));
+            assert_eq!(vec.deref(), &input[..i]);
+        }
+
+        assert_eq!(vec.pop(), None);
+        assert!(vec.spilled());
+
+        vec.shrink_to_fit();
+        assert!(!vec.spilled());
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut vec = SmallArrayVec::new();
+        let mut expected = Vec::new();
+
+        for i in 0..20 {
+            let index = i / 2;
+            vec.insert(index, i);
+            expected.insert(index, i);
+            assert_eq!(vec.deref(), expected.as_slice());
+        }
+
+        while !expected.is_empty() {
+            let index = expected.len() / 3;
+            assert_eq!(vec.remove(index), expected.remove(index));
+            assert_eq!(vec.deref(), expected.as_slice());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_insert_out_of_bounds() {
+        let mut vec = SmallArrayVec::new();
+        vec.insert(1, 0);
+    }
+
+    #[test]
+    fn test_extend_truncate() {
+        let mut vec: SmallArrayVec<u32> = (0..5).collect();
+        assert!(!vec.spilled());
+
+        vec.extend(5..30);
+        assert!(vec.spilled());
+        assert!(vec.iter().copied().eq(0..30));
+
+        vec.truncate(40);
+        assert_eq!(vec.len(), 30);
+
+        vec.truncate(3);
+        assert!(vec.iter().copied().eq(0..3));
+
+        vec.clear();
+        assert!(vec.is_empty());
+    }
+
+    #[test]
+    fn test_reserve_shrink_to_fit() {
+        let mut vec: SmallArrayVec<u32> = SmallArrayVec::with_capacity(100);
+        assert!(vec.spilled());
+        assert!(vec.capacity() >= 100);
+
+        vec.extend(0..20);
+        vec.shrink_to_fit();
+        assert!(vec.spilled());
+        assert_eq!(vec.capacity(), 20);
+
+        vec.truncate(INLINE_LEN);
+        vec.shrink_to_fit();
+        assert!(!vec.spilled());
+        assert!(vec.iter().copied().eq(0..(INLINE_LEN as u32)));
+
+        vec.reserve_exact(INLINE_LEN + 1);
+        assert_eq!(vec.capacity(), INLINE_LEN * 2 + 1);
+    }
+
+    #[test]
+    fn test_into_small_array_box() {
+        for len in 0..30 {
+            let mut vec: SmallArrayVec<u32> = SmallArrayVec::with_capacity(64);
+            vec.extend(0..len);
+
+            let array = vec.clone().into_small_array_box();
+            assert_eq!(array.deref(), vec.deref());
+
+            let vec2: SmallArrayVec<u32> = array.into();
+            assert_eq!(vec2, vec);
+            assert_eq!(vec2.spilled(), len as usize > INLINE_LEN);
+
+            assert_eq!(Vec::from(vec2), vec.deref());
+        }
+    }
+
+    #[test]
+    fn test_drop() {
+        let rc = Rc::new(());
+
+        let mut vec = SmallArrayVec::new();
+        for _ in 0..20 {
+            vec.push(rc.clone());
+        }
+        assert_eq!(Rc::strong_count(&rc), 21);
+
+        vec.truncate(10);
+        assert_eq!(Rc::strong_count(&rc), 11);
+
+        drop(vec.remove(0));
+        vec.shrink_to_fit();
+        assert_eq!(Rc::strong_count(&rc), 10);
+
+        let array = vec.clone().into_small_array_box();
+        assert_eq!(Rc::strong_count(&rc), 19);
+
+        drop(array);
+        drop(vec);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn test_zst() {
+        let mut vec: SmallArrayVec<()> = SmallArrayVec::new();
+
+        for _ in 0..100 {
+            vec.push(());
+        }
+
+        assert_eq!(vec.len(), 100);
+        assert_eq!(vec.into_small_array_box().len(), 100);
+    }
+}
@@ -0,0 +1,232 @@
+use super::SmallArrayBox;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+
+use core::borrow::Borrow;
+use core::fmt::{self, Debug, Display};
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+use core::str::{self, Utf8Error};
+
+use core::cmp::{Eq, Ord, PartialEq, PartialOrd};
+
+/// Store an immutable UTF-8 string in `SmallArrayBox<u8, INLINE_LEN>`.
+///
+/// * `INLINE_LEN` - Number of bytes that can be stored inline.
+#[derive(Default, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct SmallStr<const INLINE_LEN: usize>(SmallArrayBox<u8, INLINE_LEN>);
+
+impl<const INLINE_LEN: usize> SmallStr<INLINE_LEN> {
+    pub const fn new_empty() -> Self {
+        Self(SmallArrayBox::new_empty())
+    }
+
+    /// Return an error if `bytes` is not valid UTF-8.
+    pub fn from_utf8(bytes: SmallArrayBox<u8, INLINE_LEN>) -> Result<Self, Utf8Error> {
+        str::from_utf8(&bytes)?;
+        Ok(Self(bytes))
+    }
+
+    /// # Safety
+    ///
+    /// `bytes` must be valid UTF-8.
+    pub unsafe fn from_utf8_unchecked(bytes: SmallArrayBox<u8, INLINE_LEN>) -> Self {
+        Self(bytes)
+    }
+
+    #[inline(always)]
+    pub fn as_str(&self) -> &str {
+        unsafe { str::from_utf8_unchecked(&self.0) }
+    }
+
+    pub fn into_bytes(self) -> SmallArrayBox<u8, INLINE_LEN> {
+        self.0
+    }
+
+    pub fn into_boxed_str(self) -> Box<str> {
+        unsafe { String::from_utf8_unchecked(self.0.into()) }.into_boxed_str()
+    }
+
+    pub fn into_string(self) -> String {
+        unsafe { String::from_utf8_unchecked(self.0.into()) }
+    }
+}
+
+impl<const INLINE_LEN: usize> From<&str> for SmallStr<INLINE_LEN> {
+    fn from(s: &str) -> Self {
+        Self(s.as_bytes().into())
+    }
+}
+
+/// Take over the heap buffer of `s` if it cannot be stored inline.
+///
+/// Spare capacity is released first, as in `String::into_boxed_str`,
+/// which may reallocate, so the buffer is only guaranteed to be reused if
+/// `s.capacity() == s.len()`.
+impl<const INLINE_LEN: usize> From<String> for SmallStr<INLINE_LEN> {
+    fn from(s: String) -> Self {
+        Self(s.into_bytes().into())
+    }
+}
+
+/// Take over the heap buffer of `s` if it cannot be stored inline.
+impl<const INLINE_LEN: usize> From<Box<str>> for SmallStr<INLINE_LEN> {
+    fn from(s: Box<str>) -> Self {
+        Self(s.into_boxed_bytes().into())
+    }
+}
+
+impl<const INLINE_LEN: usize> From<SmallStr<INLINE_LEN>> for String {
+    fn from(s: SmallStr<INLINE_LEN>) -> Self {
+        s.into_string()
+    }
+}
+
+impl<const INLINE_LEN: usize> From<SmallStr<INLINE_LEN>> for Box<str> {
+    fn from(s: SmallStr<INLINE_LEN>) -> Self {
+        s.into_boxed_str()
+    }
+}
+
+impl<const INLINE_LEN: usize> Deref for SmallStr<INLINE_LEN> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<const INLINE_LEN: usize> AsRef<str> for SmallStr<INLINE_LEN> {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<const INLINE_LEN: usize> AsRef<[u8]> for SmallStr<INLINE_LEN> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<const INLINE_LEN: usize> Borrow<str> for SmallStr<INLINE_LEN> {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+/// Same as the hash of `str`, so that `Borrow<str>` can be used to look up
+/// `SmallStr` in `HashMap` and `HashSet`.
+impl<const INLINE_LEN: usize> Hash for SmallStr<INLINE_LEN> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl<const INLINE_LEN: usize> PartialEq<str> for SmallStr<INLINE_LEN> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const INLINE_LEN: usize> PartialEq<&str> for SmallStr<INLINE_LEN> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<const INLINE_LEN: usize> PartialEq<String> for SmallStr<INLINE_LEN> {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const INLINE_LEN: usize> Display for SmallStr<INLINE_LEN> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<const INLINE_LEN: usize> Debug for SmallStr<INLINE_LEN> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    type SmallStr = super::SmallStr<8>;
+
+    use super::SmallArrayBox;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn test() {
+        let input: String = ('a'..='z').chain('α'..='ω').collect();
+
+        for (len, _) in input.char_indices() {
+            let s = &input[..len];
+
+            let small_str = SmallStr::from(s);
+            assert_eq!(small_str, s);
+            assert_eq!(small_str.len(), len);
+            assert_eq!(small_str.to_string(), s);
+            assert_eq!(format!("{:?}", small_str), format!("{:?}", s));
+
+            let small_str = SmallStr::from(s.to_string());
+            assert_eq!(small_str, s);
+            assert_eq!(small_str.clone().into_string(), s);
+            assert_eq!(&*small_str.into_boxed_str(), s);
+        }
+
+        assert_eq!(SmallStr::new_empty(), "");
+    }
+
+    #[test]
+    fn test_from_string_reuses_buffer() {
+        let s = String::from("a string longer than inline storage");
+        let ptr = s.as_ptr();
+
+        let small_str = SmallStr::from(s);
+        assert_eq!(small_str.as_ptr(), ptr);
+
+        // Spare capacity is released, which may move the buffer.
+        let content = "a string longer than inline storage";
+        let mut s = String::with_capacity(content.len() * 4);
+        s.push_str(content);
+        assert!(s.capacity() > s.len());
+
+        let small_str = SmallStr::from(s);
+        assert_eq!(small_str, content);
+
+        let s = small_str.into_string();
+        assert_eq!(s, content);
+        assert_eq!(s.capacity(), content.len());
+    }
+
+    #[test]
+    fn test_from_utf8() {
+        let bytes = SmallArrayBox::from(&b"abc"[..]);
+        assert_eq!(SmallStr::from_utf8(bytes).unwrap(), "abc");
+
+        let bytes = SmallArrayBox::from(&[0xff, 0xfe][..]);
+        assert!(SmallStr::from_utf8(bytes).is_err());
+    }
+
+    #[test]
+    fn test_hash_ord() {
+        let set: HashSet<SmallStr> = ["x", "a long string"]
+            .iter()
+            .map(|s| SmallStr::from(*s))
+            .collect();
+
+        assert!(set.contains("x"));
+        assert!(set.contains("a long string"));
+        assert!(!set.contains("y"));
+
+        let mut strs: Vec<SmallStr> = ["b", "ab", "a", ""].iter().map(|s| (*s).into()).collect();
+        strs.sort();
+        assert!(strs.iter().eq(["", "a", "ab", "b"].iter()));
+    }
+}
@@ -0,0 +1,376 @@
+use super::allocator::Allocator;
+use super::{
+    Strings, StringsArena, StringsArenaIter, StringsIter, StringsNoIndex, StringsNoIndexIter,
+    TwoStrs,
+};
+
+use alloc::vec::Vec;
+
+use core::array;
+use core::convert::TryFrom;
+use core::iter::{ExactSizeIterator, Iterator, Map};
+use core::slice;
+
+/// Ordered list of strings, implemented by the containers of this crate
+/// and by slices, arrays and `Vec` of `AsRef<str>`.
+///
+/// `ConcurrentStrings` does not implement it, since its strings may not be
+/// visible yet while other threads push.
+pub trait StrList {
+    type Iter<'a>: Iterator<Item = &'a str> + ExactSizeIterator
+    where
+        Self: 'a;
+
+    /// Return the number of strings.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the total length of all strings in bytes.
+    fn strs_len(&self) -> usize {
+        self.iter().map(str::len).sum()
+    }
+
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Return true if `self` and `other` contain the same strings in the
+    /// same order.
+    fn eq_str_list<L: StrList + ?Sized>(&self, other: &L) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+/// `StrList` with random access.
+pub trait IndexedStrList: StrList {
+    fn get(&self, index: usize) -> Option<&str>;
+}
+
+impl<T: StrList + ?Sized> StrList for &T {
+    type Iter<'a>
+        = T::Iter<'a>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize {
+        T::len(self)
+    }
+
+    fn strs_len(&self) -> usize {
+        T::strs_len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        T::iter(self)
+    }
+}
+
+impl<T: IndexedStrList + ?Sized> IndexedStrList for &T {
+    fn get(&self, index: usize) -> Option<&str> {
+        T::get(self, index)
+    }
+}
+
+impl<A: Allocator> StrList for Strings<A> {
+    type Iter<'a>
+        = StringsIter<'a>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize {
+        Strings::len(self) as usize
+    }
+
+    fn strs_len(&self) -> usize {
+        Strings::strs_len(self) as usize
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        Strings::iter(self)
+    }
+}
+
+impl<A: Allocator> IndexedStrList for Strings<A> {
+    fn get(&self, index: usize) -> Option<&str> {
+        Strings::get(self, u32::try_from(index).ok()?)
+    }
+}
+
+impl<A: Allocator> StrList for StringsNoIndex<A> {
+    type Iter<'a>
+        = StringsNoIndexIter<'a>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize {
+        StringsNoIndex::len(self) as usize
+    }
+
+    fn strs_len(&self) -> usize {
+        StringsNoIndex::content_len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        StringsNoIndex::iter(self)
+    }
+}
+
+impl<A: Allocator> StrList for StringsArena<A> {
+    type Iter<'a>
+        = StringsArenaIter<'a, A>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize {
+        StringsArena::len(self) as usize
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        StringsArena::iter(self)
+    }
+}
+
+impl<A: Allocator> IndexedStrList for StringsArena<A> {
+    fn get(&self, index: usize) -> Option<&str> {
+        StringsArena::get(self, u32::try_from(index).ok()?)
+    }
+}
+
+impl<A: Allocator> StrList for TwoStrs<A> {
+    type Iter<'a>
+        = array::IntoIter<&'a str, 2>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize {
+        2
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        let (s1, s2) = TwoStrs::get(self);
+        IntoIterator::into_iter([s1, s2])
+    }
+}
+
+impl<A: Allocator> IndexedStrList for TwoStrs<A> {
+    fn get(&self, index: usize) -> Option<&str> {
+        let (s1, s2) = TwoStrs::get(self);
+        match index {
+            0 => Some(s1),
+            1 => Some(s2),
+            _ => None,
+        }
+    }
+}
+
+impl<S: AsRef<str>> StrList for [S] {
+    type Iter<'a>
+        = Map<slice::Iter<'a, S>, fn(&'a S) -> &'a str>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize {
+        <[S]>::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        <[S]>::iter(self).map(S::as_ref)
+    }
+}
+
+impl<S: AsRef<str>> IndexedStrList for [S] {
+    fn get(&self, index: usize) -> Option<&str> {
+        <[S]>::get(self, index).map(S::as_ref)
+    }
+}
+
+impl<S: AsRef<str>> StrList for Vec<S> {
+    type Iter<'a>
+        = <[S] as StrList>::Iter<'a>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        StrList::iter(&self[..])
+    }
+}
+
+impl<S: AsRef<str>> IndexedStrList for Vec<S> {
+    fn get(&self, index: usize) -> Option<&str> {
+        IndexedStrList::get(&self[..], index)
+    }
+}
+
+impl<S: AsRef<str>, const N: usize> StrList for [S; N] {
+    type Iter<'a>
+        = <[S] as StrList>::Iter<'a>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        StrList::iter(&self[..])
+    }
+}
+
+impl<S: AsRef<str>, const N: usize> IndexedStrList for [S; N] {
+    fn get(&self, index: usize) -> Option<&str> {
+        IndexedStrList::get(&self[..], index)
+    }
+}
+
+macro_rules! impl_partial_eq_between {
+    ($Lhs:ident, $Rhs:ident) => {
+        impl<A: Allocator, B: Allocator> PartialEq<$Rhs<B>> for $Lhs<A> {
+            fn eq(&self, other: &$Rhs<B>) -> bool {
+                self.eq_str_list(other)
+            }
+        }
+    };
+}
+
+impl_partial_eq_between!(Strings, StringsNoIndex);
+impl_partial_eq_between!(StringsNoIndex, Strings);
+impl_partial_eq_between!(Strings, StringsArena);
+impl_partial_eq_between!(StringsArena, Strings);
+impl_partial_eq_between!(StringsNoIndex, StringsArena);
+impl_partial_eq_between!(StringsArena, StringsNoIndex);
+
+macro_rules! impl_partial_eq_with_std {
+    ($Container:ident) => {
+        impl<A: Allocator, S: AsRef<str>> PartialEq<[S]> for $Container<A> {
+            fn eq(&self, other: &[S]) -> bool {
+                self.eq_str_list(other)
+            }
+        }
+
+        impl<A: Allocator, S: AsRef<str>, const N: usize> PartialEq<[S; N]> for $Container<A> {
+            fn eq(&self, other: &[S; N]) -> bool {
+                self.eq_str_list(other)
+            }
+        }
+
+        impl<A: Allocator, S: AsRef<str>> PartialEq<Vec<S>> for $Container<A> {
+            fn eq(&self, other: &Vec<S>) -> bool {
+                self.eq_str_list(other)
+            }
+        }
+
+        impl<A: Allocator, S: AsRef<str>> PartialEq<$Container<A>> for [S] {
+            fn eq(&self, other: &$Container<A>) -> bool {
+                self.eq_str_list(other)
+            }
+        }
+
+        impl<A: Allocator, S: AsRef<str>> PartialEq<$Container<A>> for Vec<S> {
+            fn eq(&self, other: &$Container<A>) -> bool {
+                self.eq_str_list(other)
+            }
+        }
+    };
+}
+
+impl_partial_eq_with_std!(Strings);
+impl_partial_eq_with_std!(StringsNoIndex);
+impl_partial_eq_with_std!(StringsArena);
+
+#[cfg(test)]
+mod tests {
+    use super::{IndexedStrList, StrList};
+    use crate::{Strings, StringsArena, StringsNoIndex, TwoStrs};
+
+    /// Generic code that accepts any `StrList`.
+    fn join<L: StrList + ?Sized>(list: &L) -> String {
+        let mut joined = String::with_capacity(list.strs_len() + list.len());
+        for s in list.iter() {
+            joined.push_str(s);
+            joined.push(',');
+        }
+        joined
+    }
+
+    fn last<L: IndexedStrList + ?Sized>(list: &L) -> Option<&str> {
+        list.get(list.len().checked_sub(1)?)
+    }
+
+    #[test]
+    fn test_str_list() {
+        let input = ["a", "", "bc", "def"];
+
+        let mut strings = Strings::new();
+        let mut strings_no_index = StringsNoIndex::new();
+        let arena = StringsArena::new();
+        for s in &input {
+            strings.push(s);
+            strings_no_index.push(s);
+            arena.alloc(s);
+        }
+        let owned: Vec<String> = input.iter().map(|s| s.to_string()).collect();
+
+        let expected = "a,,bc,def,";
+        assert_eq!(join(&strings), expected);
+        assert_eq!(join(&strings_no_index), expected);
+        assert_eq!(join(&arena), expected);
+        assert_eq!(join(&input), expected);
+        assert_eq!(join(&input[..]), expected);
+        assert_eq!(join(&owned), expected);
+        assert_eq!(join(&&owned[..]), expected);
+
+        assert_eq!(StrList::strs_len(&strings), 6);
+        assert_eq!(StrList::strs_len(&strings_no_index), 6);
+        assert_eq!(StrList::strs_len(&arena), 6);
+        assert_eq!(StrList::strs_len(&StringsNoIndex::new()), 0);
+
+        assert_eq!(last(&strings), Some("def"));
+        assert_eq!(last(&arena), Some("def"));
+        assert_eq!(last(&owned), Some("def"));
+        assert_eq!(last(&Strings::new()), None);
+        assert_eq!(IndexedStrList::get(&strings, 4), None);
+        assert_eq!(IndexedStrList::get(&strings, usize::MAX), None);
+
+        let two_strs = TwoStrs::new("k", "v");
+        assert_eq!(join(&two_strs), "k,v,");
+        assert_eq!(last(&two_strs), Some("v"));
+        assert!(two_strs.eq_str_list(&["k", "v"]));
+    }
+
+    #[test]
+    fn test_partial_eq() {
+        let input = ["a", "", "bc"];
+
+        let mut strings = Strings::new();
+        let mut strings_no_index = StringsNoIndex::new();
+        let arena = StringsArena::new();
+        for s in &input {
+            strings.push(s);
+            strings_no_index.push(s);
+            arena.alloc(s);
+        }
+
+        assert!(strings == strings_no_index);
+        assert!(strings_no_index == strings);
+        assert!(strings == arena);
+        assert!(arena == strings_no_index);
+
+        assert!(strings == input);
+        assert!(strings == input[..]);
+        assert!(strings == input.to_vec());
+        assert!(input.to_vec() == strings);
+        assert!(input[..] == arena);
+        assert!(strings_no_index == vec!["a".to_string(), "".to_string(), "bc".to_string()]);
+
+        assert!(strings != input[..2]);
+        assert!(strings != ["a", "", "bd"]);
+
+        strings.push("");
+        assert!(strings != strings_no_index);
+    }
+}
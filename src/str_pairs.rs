@@ -0,0 +1,520 @@
+use super::allocator::{Allocator, Global};
+use super::thin_vec_in::ThinVecIn;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use core::convert::TryInto;
+use core::hash::{Hash, Hasher};
+use core::iter::{ExactSizeIterator, Extend, FromIterator, IntoIterator, Iterator};
+use core::mem;
+use core::slice;
+use core::str;
+
+/// Store key/value string pairs efficiently, e.g. environment variables
+/// or HTTP-like headers.
+///
+/// All keys and values are stored in one buffer in insertion order,
+/// lookup by key is a linear scan.
+///
+/// Can store at most `u32::MAX / 2` pairs, the accumulated length
+/// of all keys and values can be at most `u32::MAX`.
+///
+/// * `A` - Allocator of the strings and their ends, each of them
+///   is stored in its own allocation with a clone of the allocator.
+#[derive(Debug, Default, Clone)]
+pub struct StrPairs<A: Allocator = Global> {
+    strs: ThinVecIn<u8, A>,
+    /// `ends[2 * i]` is the end of the i-th key and
+    /// `ends[2 * i + 1]` is the end of the i-th value.
+    ends: ThinVecIn<u32, A>,
+}
+
+impl StrPairs {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// * `len` - number of pairs
+    pub fn with_capacity(len: u32) -> Self {
+        Self::with_capacity_in(len, Global)
+    }
+}
+
+impl<A: Allocator + Clone> StrPairs<A> {
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            strs: ThinVecIn::new_in(alloc.clone()),
+            ends: ThinVecIn::new_in(alloc),
+        }
+    }
+
+    /// * `len` - number of pairs
+    pub fn with_capacity_in(len: u32, alloc: A) -> Self {
+        let mut pairs = Self::new_in(alloc);
+        pairs.reserve(len);
+        pairs
+    }
+}
+
+impl<A: Allocator> StrPairs<A> {
+    pub fn allocator(&self) -> &A {
+        self.strs.allocator()
+    }
+
+    /// Number of pairs.
+    #[inline(always)]
+    pub fn len(&self) -> u32 {
+        (self.ends.len() / 2) as u32
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Accumulate length of all keys and values.
+    #[inline(always)]
+    pub fn strs_len(&self) -> u32 {
+        self.strs.len() as u32
+    }
+
+    /// * `pairs_cnt` - number of pairs
+    #[inline(always)]
+    pub fn reserve(&mut self, pairs_cnt: u32) {
+        self.ends.reserve(pairs_cnt as usize * 2);
+    }
+
+    #[inline(always)]
+    pub fn reserve_strs(&mut self, cnt: usize) {
+        self.strs.reserve(cnt);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.strs.shrink_to_fit();
+        self.ends.shrink_to_fit();
+    }
+
+    pub fn clear(&mut self) {
+        self.strs.truncate(0);
+        self.ends.truncate(0);
+    }
+
+    fn push_end(&mut self) {
+        self.ends.push(
+            self.strs
+                .len()
+                .try_into()
+                .expect("StrPairs cannot contain more than u32::MAX bytes"),
+        );
+    }
+
+    /// Start of the `i`-th string, where `i` indexes into `ends`.
+    #[inline(always)]
+    fn start_of(&self, i: usize) -> u32 {
+        if i == 0 {
+            0
+        } else {
+            self.ends[i - 1]
+        }
+    }
+
+    /// Return the index of the pair with `key`.
+    pub fn position(&self, key: &str) -> Option<u32> {
+        self.iter()
+            .position(|(k, _v)| k == key)
+            .map(|index| index as u32)
+    }
+
+    /// Return the `index`-th pair in insertion order.
+    pub fn get_pair(&self, index: u32) -> Option<(&str, &str)> {
+        let i = index as usize * 2;
+        let value_end = *self.ends.get(i + 1)?;
+        let key_end = self.ends[i];
+
+        Some((
+            get_str_impl(&self.strs, self.start_of(i), key_end),
+            get_str_impl(&self.strs, key_end, value_end),
+        ))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter().find(|(k, _v)| *k == key).map(|(_k, v)| v)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// Insert `key` with `value`.
+    ///
+    /// If `key` is already present, its value is replaced in place
+    /// and the pair keeps its position.
+    pub fn insert(&mut self, key: &str, value: &str) {
+        match self.position(key) {
+            Some(index) => self.replace_value(index as usize * 2 + 1, value),
+            None => self.push_pair(key, value),
+        }
+    }
+
+    /// Append the pair without checking whether `key` is present.
+    fn push_pair(&mut self, key: &str, value: &str) {
+        self.strs.extend_from_slice(key.as_bytes());
+        self.push_end();
+        self.strs.extend_from_slice(value.as_bytes());
+        self.push_end();
+    }
+
+    /// * `i` - index of the value in `ends`
+    fn replace_value(&mut self, i: usize, value: &str) {
+        let start = self.ends[i - 1];
+        let old_end = self.ends[i];
+
+        let new_end: u32 = (start as usize + value.len())
+            .try_into()
+            .expect("StrPairs cannot contain more than u32::MAX bytes");
+        let new_strs_len = self.strs.len() - (old_end - start) as usize + value.len();
+        let _: u32 = new_strs_len
+            .try_into()
+            .expect("StrPairs cannot contain more than u32::MAX bytes");
+
+        self.strs
+            .replace_range((start as usize)..(old_end as usize), value.as_bytes());
+
+        for end in self.ends[i..].iter_mut() {
+            *end = *end - old_end + new_end;
+        }
+    }
+
+    /// Remove the pair with `key`, return `true` if it is present.
+    pub fn remove(&mut self, key: &str) -> bool {
+        let i = match self.position(key) {
+            Some(index) => index as usize * 2,
+            None => return false,
+        };
+
+        let start = self.start_of(i);
+        let end = self.ends[i + 1];
+
+        self.strs
+            .replace_range((start as usize)..(end as usize), &[]);
+        self.ends.replace_range(i..(i + 2), &[]);
+
+        let removed = end - start;
+        for end in self.ends[i..].iter_mut() {
+            *end -= removed;
+        }
+
+        true
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> StrPairsIter<'_> {
+        StrPairsIter {
+            strs: &self.strs,
+            ends_iter: self.ends.chunks_exact(2),
+            start: 0,
+        }
+    }
+
+    /// Export as an environment block: each pair is written as
+    /// `KEY=VALUE\0` and the block ends with an extra `\0`.
+    ///
+    /// Keys should not contain `=` and neither keys nor values should
+    /// contain null byte, otherwise the block cannot be parsed back.
+    pub fn to_env_block(&self) -> Vec<u8> {
+        let mut block = Vec::with_capacity(self.strs.len() + self.ends.len() + 1);
+
+        for (key, value) in self {
+            block.extend_from_slice(key.as_bytes());
+            block.push(b'=');
+            block.extend_from_slice(value.as_bytes());
+            block.push(0);
+        }
+
+        block.push(0);
+        block
+    }
+}
+
+#[inline(always)]
+fn get_str_impl(strs: &[u8], start: u32, end: u32) -> &str {
+    unsafe { str::from_utf8_unchecked(&strs[(start as usize)..(end as usize)]) }
+}
+
+/// Same as calling `insert` for each pair, except that keys are looked up
+/// by sorting them instead of scanning all pairs for each of them, which
+/// takes `O(n log n)` instead of `O(n^2)`.
+impl<K: AsRef<str>, V: AsRef<str>, A: Allocator + Clone> Extend<(K, V)> for StrPairs<A> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let items: Vec<(K, V)> = iter.into_iter().collect();
+        let key = |i: usize| items[i].0.as_ref();
+        let value = |i: usize| items[i].1.as_ref();
+
+        // Stable, so that equal keys stay in insertion order.
+        let mut sorted: Vec<usize> = (0..items.len()).collect();
+        sorted.sort_by(|i, j| key(*i).cmp(key(*j)));
+
+        // Keys already present are unique.
+        let existing_key = |index: u32| self.get_pair(index).unwrap().0;
+        let mut existing: Vec<u32> = (0..self.len()).collect();
+        existing.sort_unstable_by(|i, j| existing_key(*i).cmp(existing_key(*j)));
+
+        // Index in `items` of the new value of each pair already present.
+        let mut replaced: Vec<Option<usize>> = vec![None; self.len() as usize];
+        // Indices in `items` of the first and last pair of each new key.
+        let mut added: Vec<(usize, usize)> = Vec::new();
+
+        let mut start = 0;
+        while start < sorted.len() {
+            let first = sorted[start];
+            let mut end = start + 1;
+            while end < sorted.len() && key(sorted[end]) == key(first) {
+                end += 1;
+            }
+            let last = sorted[end - 1];
+
+            match existing.binary_search_by(|index| existing_key(*index).cmp(key(first))) {
+                Ok(pos) => replaced[existing[pos] as usize] = Some(last),
+                Err(_pos) => added.push((first, last)),
+            }
+
+            start = end;
+        }
+
+        if replaced.iter().any(Option::is_some) {
+            // Rebuild once instead of shifting the following pairs for
+            // every replaced value, into new storage from the same allocator.
+            let mut old = StrPairs::with_capacity_in(self.len(), self.allocator().clone());
+            old.reserve_strs(self.strs.len());
+            mem::swap(self, &mut old);
+
+            for ((k, v), new_value) in old.iter().zip(&replaced) {
+                match new_value {
+                    Some(i) => self.push_pair(k, value(*i)),
+                    None => self.push_pair(k, v),
+                }
+            }
+        }
+
+        // New keys are appended in the order they first appear.
+        added.sort_unstable();
+        for (first, last) in added {
+            self.push_pair(key(first), value(last));
+        }
+    }
+}
+
+impl<K: AsRef<str>, V: AsRef<str>> FromIterator<(K, V)> for StrPairs {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut pairs = Self::new();
+        pairs.extend(iter);
+        pairs
+    }
+}
+
+impl<A: Allocator> PartialEq for StrPairs<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.strs == other.strs && self.ends == other.ends
+    }
+}
+
+impl<A: Allocator> Eq for StrPairs<A> {}
+
+impl<A: Allocator> Hash for StrPairs<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.strs.hash(state);
+        self.ends.hash(state);
+    }
+}
+
+impl<'a, A: Allocator> IntoIterator for &'a StrPairs<A> {
+    type Item = (&'a str, &'a str);
+    type IntoIter = StrPairsIter<'a>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StrPairsIter<'a> {
+    strs: &'a [u8],
+    ends_iter: slice::ChunksExact<'a, u32>,
+    start: u32,
+}
+
+impl<'a> Iterator for StrPairsIter<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key_end, value_end) = match self.ends_iter.next()? {
+            [key_end, value_end] => (*key_end, *value_end),
+            _ => unreachable!(),
+        };
+        let start = self.start;
+
+        self.start = value_end;
+
+        Some((
+            get_str_impl(self.strs, start, key_end),
+            get_str_impl(self.strs, key_end, value_end),
+        ))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.ends_iter.len();
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for StrPairsIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::StrPairs;
+
+    #[test]
+    fn test() {
+        let mut pairs = StrPairs::new();
+        let input: Vec<(String, String)> = (0..256)
+            .map(|n| (format!("KEY{}", n), n.to_string()))
+            .collect();
+
+        assert!(pairs.is_empty());
+
+        for (i, (key, value)) in input.iter().enumerate() {
+            pairs.insert(key, value);
+            assert_eq!(pairs.len() as usize, i + 1);
+        }
+
+        assert!(pairs
+            .iter()
+            .eq(input.iter().map(|(k, v)| (k.as_str(), v.as_str()))));
+
+        for (i, (key, value)) in input.iter().enumerate() {
+            assert_eq!(pairs.get(key), Some(value.as_str()));
+            assert_eq!(pairs.position(key), Some(i as u32));
+            assert_eq!(
+                pairs.get_pair(i as u32),
+                Some((key.as_str(), value.as_str()))
+            );
+        }
+
+        assert_eq!(pairs.get("KEY256"), None);
+        assert_eq!(pairs.get_pair(256), None);
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut pairs = StrPairs::new();
+
+        pairs.insert("A", "1");
+        pairs.insert("B", "2");
+        pairs.insert("C", "3");
+
+        pairs.insert("B", "a longer value");
+        assert_eq!(pairs.len(), 3);
+        assert!(pairs
+            .iter()
+            .eq([("A", "1"), ("B", "a longer value"), ("C", "3")]));
+
+        pairs.insert("B", "");
+        assert!(pairs.iter().eq([("A", "1"), ("B", ""), ("C", "3")]));
+
+        pairs.insert("A", "x");
+        assert!(pairs.iter().eq([("A", "x"), ("B", ""), ("C", "3")]));
+        assert_eq!(pairs.strs_len(), 5);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut pairs: StrPairs = [("A", "1"), ("B", "22"), ("C", "333")]
+            .iter()
+            .copied()
+            .collect();
+
+        assert!(!pairs.remove("D"));
+
+        assert!(pairs.remove("B"));
+        assert!(pairs.iter().eq([("A", "1"), ("C", "333")]));
+        assert_eq!(pairs.get("C"), Some("333"));
+
+        assert!(pairs.remove("A"));
+        assert!(pairs.iter().eq([("C", "333")]));
+
+        assert!(pairs.remove("C"));
+        assert!(pairs.is_empty());
+        assert_eq!(pairs.strs_len(), 0);
+    }
+
+    #[test]
+    fn test_from_iter_dedup() {
+        let pairs: StrPairs = vec![("A", "1"), ("B", "2"), ("A", "3")]
+            .into_iter()
+            .collect();
+
+        assert!(pairs.iter().eq([("A", "3"), ("B", "2")]));
+    }
+
+    #[test]
+    fn test_extend_same_as_insert() {
+        let input: Vec<(String, String)> = (0..500)
+            .map(|n| (format!("KEY{}", (n * 7) % 128), n.to_string()))
+            .collect();
+
+        let mut inserted: StrPairs = [("KEY3", "a"), ("OTHER", "b"), ("KEY100", "c")]
+            .iter()
+            .copied()
+            .collect();
+        let mut extended = inserted.clone();
+
+        for (key, value) in &input {
+            inserted.insert(key, value);
+        }
+        extended.extend(input.iter().map(|(k, v)| (k, v)));
+
+        assert_eq!(extended, inserted);
+        assert_eq!(extended.get("OTHER"), Some("b"));
+        assert_eq!(extended.position("KEY100"), Some(2));
+
+        extended.extend(Vec::<(&str, &str)>::new());
+        assert_eq!(extended, inserted);
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn test_allocator() {
+        use crate::allocator::tests::CountingAllocator;
+
+        let alloc = CountingAllocator::default();
+
+        let mut pairs = super::StrPairs::with_capacity_in(4, &alloc);
+        assert_eq!(alloc.live(), 1);
+
+        pairs.insert("A", "1");
+        pairs.insert("B", "2");
+        assert_eq!(alloc.live(), 2);
+
+        pairs.extend([("A", "3"), ("C", "4")]);
+        assert!(pairs.iter().eq([("A", "3"), ("B", "2"), ("C", "4")]));
+        assert_eq!(alloc.live(), 2);
+
+        assert!(pairs.remove("B"));
+        assert_eq!(pairs.clone(), pairs);
+
+        drop(pairs);
+        assert_eq!(alloc.live(), 0);
+    }
+
+    #[test]
+    fn test_to_env_block() {
+        assert_eq!(StrPairs::new().to_env_block(), b"\0");
+
+        let pairs: StrPairs = [("PATH", "/bin"), ("EMPTY", "")].iter().copied().collect();
+
+        assert_eq!(pairs.to_env_block(), b"PATH=/bin\0EMPTY=\0\0");
+    }
+}
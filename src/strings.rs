@@ -1,19 +1,31 @@
-use std::convert::TryInto;
-use std::hint::unreachable_unchecked;
-use std::iter::{ExactSizeIterator, IntoIterator, Iterator};
-use std::slice;
-use std::str;
-
-use thin_vec::ThinVec;
+use super::allocator::{Allocator, Global};
+use super::thin_vec_in::ThinVecIn;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use core::borrow::Borrow;
+use core::convert::TryInto;
+use core::hash::{Hash, Hasher};
+use core::hint::unreachable_unchecked;
+use core::iter::{
+    DoubleEndedIterator, ExactSizeIterator, Extend, FromIterator, IntoIterator, Iterator,
+};
+use core::ops::Range;
+use core::slice;
+use core::str;
 
 /// Store any string efficiently in an immutable way.
 ///
 /// Can store at most `u32::MAX` strings, the accumulated length
 /// of these strings can be at most `u32::MAX`.
-#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
-pub struct Strings {
-    strs: ThinVec<u8>,
-    ends: ThinVec<u32>,
+///
+/// * `A` - Allocator of the strings and their ends, each of them
+///   is stored in its own allocation with a clone of the allocator.
+#[derive(Debug, Default, Clone)]
+pub struct Strings<A: Allocator = Global> {
+    strs: ThinVecIn<u8, A>,
+    ends: ThinVecIn<u32, A>,
 }
 
 impl Strings {
@@ -24,10 +36,57 @@ impl Strings {
 
     /// * `len` - number of strings
     pub fn with_capacity(len: u32) -> Self {
-        let mut strings = Self::default();
+        Self::with_capacity_in(len, Global)
+    }
+}
+
+impl Strings {
+    /// Concatenate `strings_iter` into one `Strings`, reserving the total
+    /// length up front.
+    pub fn concat<B, S>(strings_iter: impl IntoIterator<Item = S>) -> Self
+    where
+        B: Allocator,
+        S: Borrow<Strings<B>>,
+    {
+        let strings_vec: Vec<S> = strings_iter.into_iter().collect();
+
+        let len: usize = strings_vec.iter().map(|s| s.borrow().len() as usize).sum();
+        let strs_len: usize = strings_vec
+            .iter()
+            .map(|s| s.borrow().strs_len() as usize)
+            .sum();
+
+        let mut concated = Self::with_capacity(len.min(u32::MAX as usize) as u32);
+        concated.reserve_strs(strs_len);
+
+        for strings in &strings_vec {
+            concated.extend_from_strings(strings.borrow());
+        }
+
+        concated
+    }
+}
+
+impl<A: Allocator + Clone> Strings<A> {
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            strs: ThinVecIn::new_in(alloc.clone()),
+            ends: ThinVecIn::new_in(alloc),
+        }
+    }
+
+    /// * `len` - number of strings
+    pub fn with_capacity_in(len: u32, alloc: A) -> Self {
+        let mut strings = Self::new_in(alloc);
         strings.reserve(len);
         strings
     }
+}
+
+impl<A: Allocator> Strings<A> {
+    pub fn allocator(&self) -> &A {
+        self.strs.allocator()
+    }
 
     /// **Strings can contain at most `u32::MAX` strings**
     pub fn push(&mut self, s: &str) {
@@ -79,14 +138,127 @@ impl Strings {
 
     #[inline(always)]
     pub fn iter(&self) -> StringsIter<'_> {
-        StringsIter {
+        StringsIter::new(&self.strs, &self.ends, 0)
+    }
+
+    /// Return the concatenated strings and the end of each string.
+    pub(crate) fn raw_parts(&self) -> (&[u8], &[u32]) {
+        (&self.strs, &self.ends)
+    }
+
+    /// Same as `Strings::raw_parts`, the caller must keep `ends`
+    /// consistent with `strs`.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn raw_parts_mut(&mut self) -> (&mut [u8], &mut [u32]) {
+        (&mut self.strs, &mut self.ends)
+    }
+
+    /// Append all strings of `other`, shifting its ends by the
+    /// length of `self`.
+    pub(crate) fn extend_from_strings<B: Allocator>(&mut self, other: &Strings<B>) {
+        let offset = self.strs_len();
+
+        let _: u32 = (self.strs.len() + other.strs.len())
+            .try_into()
+            .expect("Strings cannot contain more than u32::MAX bytes");
+        let _: u32 = (self.ends.len() + other.ends.len())
+            .try_into()
+            .expect("Strings cannot contain more than u32::MAX strings");
+
+        self.strs.extend_from_slice(&other.strs);
+        self.ends.extend(other.ends.iter().map(|end| end + offset));
+    }
+
+    /// Move all strings of `other` to the end of `self`, leaving `other`
+    /// empty.
+    ///
+    /// The bytes are copied with one memcpy and the ends of `other` are
+    /// shifted by `self.strs_len()`.
+    pub fn append<B: Allocator>(&mut self, other: &mut Strings<B>) {
+        self.extend_from_strings(other);
+
+        other.strs.truncate(0);
+        other.ends.truncate(0);
+    }
+
+    /// Split into two at `index`: `self` keeps strings in `[0, index)` and
+    /// strings in `[index, len)` are returned.
+    ///
+    /// Panics if `index > len`.
+    pub fn split_off(&mut self, index: u32) -> Self
+    where
+        A: Clone,
+    {
+        assert!(
+            index <= self.len(),
+            "split_off index {} out of bounds, len is {}",
+            index,
+            self.len()
+        );
+
+        let index = index as usize;
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+
+        let mut other = Self::new_in(self.allocator().clone());
+        other.strs.extend_from_slice(&self.strs[(start as usize)..]);
+        other
+            .ends
+            .extend(self.ends[index..].iter().map(|end| end - start));
+
+        self.strs.truncate(start as usize);
+        self.ends.truncate(index);
+
+        other
+    }
+
+    pub fn get(&self, index: u32) -> Option<&str> {
+        let end = *self.ends.get(index as usize)?;
+        let start = if index == 0 {
+            0
+        } else {
+            self.ends[(index - 1) as usize]
+        };
+
+        Some(get_str_impl(&self.strs, start, end))
+    }
+
+    /// Keep only the strings for which `f` returns `true`, in order.
+    ///
+    /// The kept strings are moved towards the front of the buffer in
+    /// one pass.
+    pub fn retain(&mut self, mut f: impl FnMut(&str) -> bool) {
+        let mut guard = RetainGuard {
             strings: self,
-            ends_iter: self.ends.iter(),
+            processed: 0,
+            kept: 0,
             start: 0,
+            write_end: 0,
+        };
+
+        while guard.processed < guard.strings.ends.len() {
+            let start = guard.start;
+            let end = guard.strings.ends[guard.processed];
+
+            if f(get_str_impl(&guard.strings.strs, start, end)) {
+                let write_end = guard.write_end + (end - start);
+
+                guard
+                    .strings
+                    .strs
+                    .copy_within((start as usize)..(end as usize), guard.write_end as usize);
+                guard.strings.ends[guard.kept] = write_end;
+
+                guard.write_end = write_end;
+                guard.kept += 1;
+            }
+
+            guard.start = end;
+            guard.processed += 1;
         }
     }
 
-    pub fn get(&self, index: u32) -> Option<&str> {
+    /// Return the range of the `index`-th string in `as_str()`.
+    pub fn byte_range(&self, index: u32) -> Option<Range<usize>> {
         let end = *self.ends.get(index as usize)?;
         let start = if index == 0 {
             0
@@ -94,16 +266,63 @@ impl Strings {
             self.ends[(index - 1) as usize]
         };
 
-        Some(self.get_str_impl(start, end))
+        Some((start as usize)..(end as usize))
     }
 
-    #[inline(always)]
-    fn get_str_impl(&self, start: u32, end: u32) -> &str {
-        unsafe { str::from_utf8_unchecked(&self.strs[(start as usize)..(end as usize)]) }
+    /// Return the index and the range of the string containing `offset`
+    /// of `as_str()`, found by binary search on the ends.
+    ///
+    /// Empty strings contain no byte, so they are never returned.
+    pub fn entry_at_byte(&self, offset: usize) -> Option<(u32, Range<usize>)> {
+        if offset >= self.strs.len() {
+            return None;
+        }
+
+        let index = self.ends.partition_point(|end| *end as usize <= offset) as u32;
+        let range = self.byte_range(index)?;
+
+        Some((index, range))
+    }
+
+    /// Return the first index in `range` for which `pred` is false,
+    /// assuming it is true for all the strings before it.
+    fn partition_point(&self, range: Range<u32>, mut pred: impl FnMut(&str) -> bool) -> u32 {
+        let (mut start, mut end) = (range.start, range.end);
+
+        while start < end {
+            let mid = start + (end - start) / 2;
+            if pred(self.get(mid).unwrap()) {
+                start = mid + 1;
+            } else {
+                end = mid;
+            }
+        }
+
+        start
+    }
+
+    /// Return the range of indices of the strings starting with `prefix`,
+    /// found by binary search.
+    ///
+    /// `self` must be sorted, otherwise the range returned is unspecified.
+    pub fn prefix_range(&self, prefix: &str) -> Range<u32> {
+        let start = self.partition_point(0..self.len(), |s| s < prefix);
+        let end = self.partition_point(start..self.len(), |s| s.starts_with(prefix));
+
+        start..end
+    }
+
+    /// Same as `Strings::iter`, except that the index and the range in
+    /// `as_str()` of each string are also yielded.
+    pub fn iter_with_ranges(&self) -> StringsIterWithRanges<'_> {
+        StringsIterWithRanges {
+            iter: self.iter(),
+            index: 0,
+        }
     }
 
     pub fn as_str(&self) -> &str {
-        self.get_str_impl(0, self.strs_len())
+        get_str_impl(&self.strs, 0, self.strs_len())
     }
 
     pub fn into_str(self) -> String {
@@ -112,7 +331,81 @@ impl Strings {
         unsafe { String::from_utf8_unchecked(vec) }
     }
 }
-impl<'a> IntoIterator for &'a Strings {
+
+/// Closes the gap left by the removed strings on drop, so that `Strings`
+/// stays valid even if the predicate of `Strings::retain` panics.
+struct RetainGuard<'a, A: Allocator> {
+    strings: &'a mut Strings<A>,
+    /// Number of strings passed to the predicate.
+    processed: usize,
+    kept: usize,
+    /// End of the last processed string.
+    start: u32,
+    /// End of the last kept string.
+    write_end: u32,
+}
+
+impl<A: Allocator> Drop for RetainGuard<'_, A> {
+    fn drop(&mut self) {
+        let strs = &mut self.strings.strs;
+        let ends = &mut self.strings.ends;
+
+        let gap = self.start - self.write_end;
+        let strs_len = strs.len() - gap as usize;
+        let ends_len = ends.len() - (self.processed - self.kept);
+
+        strs.copy_within((self.start as usize).., self.write_end as usize);
+        ends.copy_within(self.processed.., self.kept);
+
+        strs.truncate(strs_len);
+        ends.truncate(ends_len);
+
+        for end in ends[self.kept..].iter_mut() {
+            *end -= gap;
+        }
+    }
+}
+
+#[inline(always)]
+fn get_str_impl(strs: &[u8], start: u32, end: u32) -> &str {
+    unsafe { str::from_utf8_unchecked(&strs[(start as usize)..(end as usize)]) }
+}
+
+impl<A: Allocator> PartialEq for Strings<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.strs == other.strs && self.ends == other.ends
+    }
+}
+
+impl<A: Allocator> Eq for Strings<A> {}
+
+impl<A: Allocator> Hash for Strings<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.strs.hash(state);
+        self.ends.hash(state);
+    }
+}
+
+impl<A: Allocator, S: AsRef<str>> Extend<S> for Strings<A> {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0.min(u32::MAX as usize) as u32);
+
+        for s in iter {
+            self.push(s.as_ref());
+        }
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for Strings {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut strings = Strings::new();
+        strings.extend(iter);
+        strings
+    }
+}
+
+impl<'a, A: Allocator> IntoIterator for &'a Strings<A> {
     type Item = &'a str;
     type IntoIter = StringsIter<'a>;
 
@@ -124,11 +417,22 @@ impl<'a> IntoIterator for &'a Strings {
 
 #[derive(Clone, Debug)]
 pub struct StringsIter<'a> {
-    strings: &'a Strings,
+    strs: &'a [u8],
     ends_iter: slice::Iter<'a, u32>,
     start: u32,
 }
 
+impl<'a> StringsIter<'a> {
+    /// * `start` - start of the string ending at `ends[0]`.
+    pub(crate) fn new(strs: &'a [u8], ends: &'a [u32], start: u32) -> Self {
+        Self {
+            strs,
+            ends_iter: ends.iter(),
+            start,
+        }
+    }
+}
+
 impl<'a> Iterator for StringsIter<'a> {
     type Item = &'a str;
 
@@ -138,7 +442,7 @@ impl<'a> Iterator for StringsIter<'a> {
 
         self.start = end;
 
-        Some(self.strings.get_str_impl(start, end))
+        Some(get_str_impl(self.strs, start, end))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -147,8 +451,48 @@ impl<'a> Iterator for StringsIter<'a> {
     }
 }
 
+impl<'a> DoubleEndedIterator for StringsIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let end = *self.ends_iter.next_back()?;
+        let start = self
+            .ends_iter
+            .as_slice()
+            .last()
+            .copied()
+            .unwrap_or(self.start);
+
+        Some(get_str_impl(self.strs, start, end))
+    }
+}
+
 impl ExactSizeIterator for StringsIter<'_> {}
 
+#[derive(Clone, Debug)]
+pub struct StringsIterWithRanges<'a> {
+    iter: StringsIter<'a>,
+    index: u32,
+}
+
+impl<'a> Iterator for StringsIterWithRanges<'a> {
+    type Item = (u32, Range<usize>, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.iter.start as usize;
+        let s = self.iter.next()?;
+        let index = self.index;
+
+        self.index += 1;
+
+        Some((index, start..(start + s.len()), s))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl ExactSizeIterator for StringsIterWithRanges<'_> {}
+
 #[cfg(test)]
 mod tests {
     use super::Strings;
@@ -175,6 +519,7 @@ mod tests {
         }
 
         assert!(input_strs.iter().eq(strs.iter()));
+        assert!(input_strs.iter().rev().eq(strs.iter().rev()));
 
         for (i, input_str) in input_strs.iter().enumerate() {
             assert_eq!(strs.get(i.try_into().unwrap()).unwrap(), input_str);
@@ -185,4 +530,190 @@ mod tests {
         assert_eq!(strs.as_str(), input_str);
         assert_eq!(strs.into_str(), input_str);
     }
+
+    #[test]
+    fn test_from_iter() {
+        let input_strs: Vec<String> = (0..256).map(|n| n.to_string()).collect();
+
+        let strs: Strings = input_strs.iter().collect();
+        assert!(input_strs.iter().eq(strs.iter()));
+
+        let mut strs: Strings = ["a", "", "bc"].iter().collect();
+        strs.extend(input_strs.clone());
+        strs.extend(Vec::<&str>::new());
+        assert_eq!(strs.len() as usize, input_strs.len() + 3);
+        assert!(strs.iter().take(3).eq(["a", "", "bc"]));
+        assert!(strs.iter().skip(3).eq(input_strs.iter()));
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn test_allocator() {
+        use crate::allocator::tests::CountingAllocator;
+
+        let alloc = CountingAllocator::default();
+        let input_strs: Vec<String> = (0..256).map(|n| n.to_string()).collect();
+
+        let mut strs = super::Strings::with_capacity_in(256, &alloc);
+        assert_eq!(alloc.live(), 1);
+
+        for input_str in &input_strs {
+            strs.push(input_str);
+        }
+        assert_eq!(alloc.live(), 2);
+        assert!(input_strs.iter().eq(strs.iter()));
+
+        let cloned = strs.clone();
+        assert_eq!(cloned, strs);
+        assert_eq!(alloc.live(), 4);
+
+        drop(strs);
+        drop(cloned);
+        assert_eq!(alloc.live(), 0);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut strs: Strings = ["a", "bc"].iter().collect();
+        let mut other: Strings = ["", "def", "g"].iter().collect();
+
+        strs.append(&mut other);
+        assert!(strs.iter().eq(["a", "bc", "", "def", "g"]));
+        assert_eq!(strs.get(3), Some("def"));
+        assert!(other.is_empty());
+        assert_eq!(other.strs_len(), 0);
+
+        strs.append(&mut other);
+        assert_eq!(strs.len(), 5);
+
+        let mut empty = Strings::new();
+        empty.append(&mut strs);
+        assert!(empty.iter().eq(["a", "bc", "", "def", "g"]));
+    }
+
+    #[test]
+    fn test_split_off() {
+        let input = ["a", "bc", "", "def", "g"];
+
+        for index in 0..=input.len() {
+            let mut strs: Strings = input.iter().collect();
+            let other = strs.split_off(index as u32);
+
+            assert!(strs.iter().eq(input[..index].iter().copied()));
+            assert!(other.iter().eq(input[index..].iter().copied()));
+            assert_eq!(strs.as_str(), input[..index].concat());
+            assert_eq!(other.as_str(), input[index..].concat());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_split_off_out_of_bounds() {
+        ["a"].iter().collect::<Strings>().split_off(2);
+    }
+
+    #[test]
+    fn test_concat() {
+        let shards = vec![
+            ["a", "bc"].iter().collect::<Strings>(),
+            Strings::new(),
+            ["", "def"].iter().collect::<Strings>(),
+            ["g"].iter().collect::<Strings>(),
+        ];
+
+        let strs = Strings::concat(&shards);
+        assert!(strs.iter().eq(["a", "bc", "", "def", "g"]));
+
+        let strs = Strings::concat(shards);
+        assert_eq!(strs.as_str(), "abcdefg");
+
+        assert!(Strings::concat(Vec::<Strings>::new()).is_empty());
+    }
+
+    #[test]
+    fn test_byte_ranges() {
+        let input = ["a", "bc", "", "def", "", "g"];
+        let strs: Strings = input.iter().collect();
+
+        let mut start = 0;
+        for (i, s) in input.iter().enumerate() {
+            let range = start..(start + s.len());
+            assert_eq!(strs.byte_range(i as u32), Some(range.clone()));
+            assert_eq!(&strs.as_str()[range], *s);
+            start += s.len();
+        }
+        assert_eq!(strs.byte_range(input.len() as u32), None);
+
+        let expected = [0, 1, 1, 3, 3, 3, 5];
+        for (offset, index) in expected.iter().enumerate() {
+            let (i, range) = strs.entry_at_byte(offset).unwrap();
+            assert_eq!(i, *index);
+            assert_eq!(Some(range), strs.byte_range(i));
+        }
+        assert_eq!(strs.entry_at_byte(7), None);
+        assert_eq!(Strings::new().entry_at_byte(0), None);
+
+        let iter = strs.iter_with_ranges();
+        assert_eq!(iter.len(), input.len());
+        for (i, range, s) in iter {
+            assert_eq!(s, input[i as usize]);
+            assert_eq!(Some(range), strs.byte_range(i));
+        }
+    }
+
+    #[test]
+    fn test_prefix_range() {
+        let input = ["", "app", "append", "apply", "b", "ba", "bb", "c"];
+        let strs: Strings = input.iter().collect();
+
+        for prefix in [
+            "", "a", "ap", "app", "appe", "apx", "b", "ba", "c", "d", "0",
+        ] {
+            let range = strs.prefix_range(prefix);
+            let expected: Vec<_> = input.iter().filter(|s| s.starts_with(prefix)).collect();
+
+            assert_eq!(range.len(), expected.len(), "{:?}", prefix);
+            assert!(range
+                .clone()
+                .map(|i| strs.get(i).unwrap())
+                .eq(expected.into_iter().copied()));
+        }
+
+        assert_eq!(strs.prefix_range("d"), 8..8);
+        assert_eq!(strs.prefix_range("0"), 1..1);
+        assert_eq!(Strings::new().prefix_range("a"), 0..0);
+    }
+
+    #[test]
+    fn test_retain() {
+        let input = ["a", "bc", "", "def", "g", "hi"];
+
+        let mut strs: Strings = input.iter().collect();
+        strs.retain(|s| s.len() != 1);
+        assert!(strs.iter().eq(["bc", "", "def", "hi"]));
+        assert_eq!(strs.as_str(), "bcdefhi");
+
+        strs.retain(|_| false);
+        assert!(strs.is_empty());
+        assert_eq!(strs.strs_len(), 0);
+    }
+
+    #[test]
+    fn test_retain_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let input = ["a", "bc", "", "def", "g", "hi"];
+        let mut strs: Strings = input.iter().collect();
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            strs.retain(|s| {
+                assert_ne!(s, "g");
+                s != "bc"
+            })
+        }));
+        assert!(res.is_err());
+
+        assert!(strs.iter().eq(["a", "", "def", "g", "hi"]));
+        assert_eq!(strs.as_str(), "adefghi");
+    }
 }
@@ -0,0 +1,290 @@
+use super::allocator::{allocate_array, deallocate_array, Allocator, Global};
+use super::thin_vec_in::ThinVecIn;
+use super::Strings;
+
+use core::cell::{Cell, RefCell};
+use core::cmp;
+use core::fmt::{self, Debug};
+use core::iter::{DoubleEndedIterator, ExactSizeIterator, IntoIterator, Iterator};
+use core::ptr::{self, NonNull};
+use core::slice;
+use core::str;
+
+const MIN_CHUNK_LEN: usize = 4096;
+
+/// Arena of strings, whose `alloc` takes `&self` and returns `&str`
+/// that stay valid while more strings are allocated.
+///
+/// Bytes are stored in chunks that are never reallocated, each new chunk
+/// is at least twice as large as the previous one.
+///
+/// Strings keep their order and indices, so that the arena can be
+/// iterated and converted into `Strings`.
+///
+/// Can store at most `u32::MAX` strings.
+///
+/// * `A` - Allocator of the chunks and the indices.
+pub struct StringsArena<A: Allocator = Global> {
+    /// Start and length of each chunk, only the last one has room left.
+    chunks: RefCell<ThinVecIn<(NonNull<u8>, usize), A>>,
+    /// Number of bytes used in the last chunk.
+    used: Cell<usize>,
+    /// Start and length of each string.
+    entries: RefCell<ThinVecIn<(NonNull<u8>, usize), A>>,
+    alloc: A,
+}
+
+unsafe impl<A: Allocator + Send> Send for StringsArena<A> {}
+
+impl Default for StringsArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StringsArena {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<A: Allocator + Clone> StringsArena<A> {
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            chunks: RefCell::new(ThinVecIn::new_in(alloc.clone())),
+            used: Cell::new(0),
+            entries: RefCell::new(ThinVecIn::new_in(alloc.clone())),
+            alloc,
+        }
+    }
+}
+
+impl<A: Allocator> StringsArena<A> {
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    /// Return room for `len` bytes, `len` must not be 0.
+    fn reserve(&self, len: usize) -> NonNull<u8> {
+        let mut chunks = self.chunks.borrow_mut();
+
+        if let Some((start, chunk_len)) = chunks.last().copied() {
+            let used = self.used.get();
+            if chunk_len - used >= len {
+                self.used.set(used + len);
+                return unsafe { NonNull::new_unchecked(start.as_ptr().add(used)) };
+            }
+        }
+
+        let last_len = chunks.last().map_or(0, |(_start, chunk_len)| *chunk_len);
+        let chunk_len = cmp::max(cmp::max(last_len * 2, MIN_CHUNK_LEN), len);
+        let start = allocate_array::<u8, A>(&self.alloc, chunk_len);
+
+        chunks.push((start, chunk_len));
+        self.used.set(len);
+
+        start
+    }
+
+    /// Copy `s` into the arena and return the copy.
+    pub fn alloc(&self, s: &str) -> &str {
+        let len = self.len();
+        assert!(
+            len < u32::MAX,
+            "StringsArena cannot contain more than u32::MAX strings"
+        );
+
+        let start = if s.is_empty() {
+            NonNull::dangling()
+        } else {
+            let start = self.reserve(s.len());
+            unsafe { ptr::copy_nonoverlapping(s.as_ptr(), start.as_ptr(), s.len()) };
+            start
+        };
+
+        self.entries.borrow_mut().push((start, s.len()));
+
+        // The bytes are never modified nor moved until `self` is dropped.
+        unsafe { str::from_utf8_unchecked(slice::from_raw_parts(start.as_ptr(), s.len())) }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.entries.borrow().len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: u32) -> Option<&str> {
+        let (start, len) = *self.entries.borrow().get(index as usize)?;
+
+        Some(unsafe { str::from_utf8_unchecked(slice::from_raw_parts(start.as_ptr(), len)) })
+    }
+
+    /// Iterate over the strings allocated before this call.
+    pub fn iter(&self) -> StringsArenaIter<'_, A> {
+        StringsArenaIter {
+            arena: self,
+            start: 0,
+            end: self.len(),
+        }
+    }
+
+    /// Copy all strings into `Strings`, which stores them without the
+    /// unused space at the end of the chunks.
+    pub fn to_strings(&self) -> Strings {
+        let mut strings = Strings::with_capacity(self.len());
+        for s in self.iter() {
+            strings.push(s);
+        }
+        strings
+    }
+}
+
+impl<A: Allocator> Drop for StringsArena<A> {
+    fn drop(&mut self) {
+        for (start, chunk_len) in self.chunks.get_mut().iter() {
+            unsafe { deallocate_array(&self.alloc, *start, *chunk_len) };
+        }
+    }
+}
+
+impl<A: Allocator> Debug for StringsArena<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, A: Allocator> IntoIterator for &'a StringsArena<A> {
+    type Item = &'a str;
+    type IntoIter = StringsArenaIter<'a, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Strings allocated in the arena while iterating are not yielded.
+pub struct StringsArenaIter<'a, A: Allocator = Global> {
+    arena: &'a StringsArena<A>,
+    start: u32,
+    end: u32,
+}
+
+impl<A: Allocator> Clone for StringsArenaIter<'_, A> {
+    fn clone(&self) -> Self {
+        Self {
+            arena: self.arena,
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+impl<A: Allocator> Debug for StringsArenaIter<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a, A: Allocator> Iterator for StringsArenaIter<'a, A> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start == self.end {
+            return None;
+        }
+
+        let s = self.arena.get(self.start);
+        self.start += 1;
+        s
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.start) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<A: Allocator> ExactSizeIterator for StringsArenaIter<'_, A> {}
+
+impl<A: Allocator> DoubleEndedIterator for StringsArenaIter<'_, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.start == self.end {
+            return None;
+        }
+
+        self.end -= 1;
+        self.arena.get(self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StringsArena, MIN_CHUNK_LEN};
+
+    #[test]
+    fn test() {
+        let arena = StringsArena::new();
+        assert!(arena.is_empty());
+        assert_eq!(arena.get(0), None);
+
+        let long = "x".repeat(MIN_CHUNK_LEN * 3);
+        let input: Vec<String> = (0..1000)
+            .map(|n| n.to_string())
+            .chain([String::new(), long])
+            .collect();
+
+        // All returned strs are still borrowed while allocating.
+        let allocated: Vec<&str> = input.iter().map(|s| arena.alloc(s)).collect();
+
+        assert_eq!(allocated, input);
+        assert_eq!(arena.len() as usize, input.len());
+        assert_eq!(arena.get(999), Some("999"));
+        assert_eq!(arena.get(input.len() as u32), None);
+
+        assert!(arena.iter().eq(input.iter()));
+        assert!(arena.iter().rev().eq(input.iter().rev()));
+        assert_eq!(arena.iter().len(), input.len());
+
+        let strings = arena.to_strings();
+        assert!(strings.iter().eq(input.iter()));
+        assert_eq!(strings.strs_len() as usize, input.concat().len());
+    }
+
+    #[test]
+    fn test_alloc_while_iterating() {
+        let arena = StringsArena::new();
+        arena.alloc("a");
+        arena.alloc("b");
+
+        for s in &arena {
+            arena.alloc(&format!("{}{}", s, s));
+        }
+
+        assert!(arena.iter().eq(["a", "b", "aa", "bb"]));
+        assert_eq!(format!("{:?}", arena), r#"["a", "b", "aa", "bb"]"#);
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn test_allocator() {
+        use crate::allocator::tests::CountingAllocator;
+
+        let alloc = CountingAllocator::default();
+
+        let arena = StringsArena::new_in(&alloc);
+        assert_eq!(alloc.live(), 0);
+
+        // The chunk and the indices of the chunks and strings.
+        arena.alloc("a");
+        assert_eq!(alloc.live(), 3);
+
+        arena.alloc(&"x".repeat(MIN_CHUNK_LEN));
+        assert_eq!(alloc.live(), 4);
+
+        drop(arena);
+        assert_eq!(alloc.live(), 0);
+    }
+}
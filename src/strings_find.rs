@@ -0,0 +1,163 @@
+use super::allocator::Allocator;
+use super::Strings;
+
+use core::iter::{FusedIterator, Iterator};
+
+use memchr::memmem::Finder;
+
+impl<A: Allocator> Strings<A> {
+    /// Search `pattern` in all strings in one pass over `as_str()` and
+    /// yield `(index, offset)` of every match, where `offset` is relative
+    /// to the start of the `index`-th string.
+    ///
+    /// Matches within one string do not overlap, matches spanning more
+    /// than one string are skipped.
+    ///
+    /// An empty `pattern` matches once at the start of every string.
+    pub fn find_iter<'a, 'p>(&'a self, pattern: &'p str) -> StringsFindIter<'a, 'p> {
+        let (strs, ends) = self.raw_parts();
+
+        StringsFindIter {
+            finder: Finder::new(pattern.as_bytes()),
+            strs,
+            ends,
+            pos: 0,
+            next_empty_match: 0,
+        }
+    }
+
+    /// Same as `Strings::find_iter`, except that only the index of each
+    /// string containing `pattern` is yielded, once.
+    pub fn find_entries<'a, 'p>(&'a self, pattern: &'p str) -> StringsFindEntries<'a, 'p> {
+        StringsFindEntries(self.find_iter(pattern))
+    }
+}
+
+/// Created by `Strings::find_iter`.
+#[derive(Clone, Debug)]
+pub struct StringsFindIter<'a, 'p> {
+    finder: Finder<'p>,
+    strs: &'a [u8],
+    ends: &'a [u32],
+    /// Position in `strs` to resume searching from.
+    pos: usize,
+    /// Index of the next string to match if `pattern` is empty.
+    next_empty_match: usize,
+}
+
+impl StringsFindIter<'_, '_> {
+    fn start_of(&self, index: usize) -> usize {
+        if index == 0 {
+            0
+        } else {
+            self.ends[index - 1] as usize
+        }
+    }
+
+    /// Return `(index, offset)` of the next match and skip the rest of
+    /// the string if `skip_entry`.
+    fn next_match(&mut self, skip_entry: bool) -> Option<(u32, u32)> {
+        let pattern_len = self.finder.needle().len();
+
+        if pattern_len == 0 {
+            let index = self.next_empty_match;
+            if index >= self.ends.len() {
+                return None;
+            }
+
+            self.next_empty_match += 1;
+            return Some((index as u32, 0));
+        }
+
+        loop {
+            let pos = self.pos + self.finder.find(self.strs.get(self.pos..)?)?;
+
+            // The first string ending after `pos` contains it,
+            // empty strings are skipped since their end equals their start.
+            let index = self.ends.partition_point(|end| *end as usize <= pos);
+            let end = self.ends[index] as usize;
+
+            if pos + pattern_len <= end {
+                self.pos = if skip_entry { end } else { pos + pattern_len };
+
+                return Some((index as u32, (pos - self.start_of(index)) as u32));
+            }
+
+            // Any later match starting in this string also spans its end.
+            self.pos = end;
+        }
+    }
+}
+
+impl Iterator for StringsFindIter<'_, '_> {
+    type Item = (u32, u32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_match(false)
+    }
+}
+
+impl FusedIterator for StringsFindIter<'_, '_> {}
+
+/// Created by `Strings::find_entries`.
+#[derive(Clone, Debug)]
+pub struct StringsFindEntries<'a, 'p>(StringsFindIter<'a, 'p>);
+
+impl Iterator for StringsFindEntries<'_, '_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_match(true).map(|(index, _offset)| index)
+    }
+}
+
+impl FusedIterator for StringsFindEntries<'_, '_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::Strings;
+
+    /// Same as `find_iter`, implemented with `str::match_indices`.
+    fn naive_find(input: &[&str], pattern: &str) -> Vec<(u32, u32)> {
+        input
+            .iter()
+            .enumerate()
+            .flat_map(|(i, s)| {
+                s.match_indices(pattern)
+                    .map(move |(offset, _)| (i as u32, offset as u32))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let input = ["ab", "aba", "", "bab", "aaaa", "b", "ab"];
+        let strs: Strings = input.iter().collect();
+
+        for pattern in ["a", "b", "ab", "ba", "aba", "aa", "bab", "abab", "c"] {
+            let found: Vec<_> = strs.find_iter(pattern).collect();
+            assert_eq!(found, naive_find(&input, pattern), "pattern {:?}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_find_across_boundary() {
+        let strs: Strings = ["xab", "cx"].iter().collect();
+
+        assert_eq!(strs.as_str().find("abc"), Some(1));
+        assert_eq!(strs.find_iter("abc").next(), None);
+        assert_eq!(strs.find_entries("bc").next(), None);
+        assert!(strs.find_iter("x").eq([(0, 0), (1, 1)]));
+    }
+
+    #[test]
+    fn test_find_entries() {
+        let strs: Strings = ["aa", "b", "", "aba", "a"].iter().collect();
+
+        assert!(strs.find_entries("a").eq([0, 3, 4]));
+        assert!(strs.find_entries("ab").eq([3]));
+        assert!(strs.find_entries("").eq(0..5));
+        assert!(strs.find_iter("").eq((0..5).map(|i| (i, 0))));
+        assert_eq!(Strings::new().find_iter("a").next(), None);
+    }
+}
@@ -1,16 +1,20 @@
-use std::convert::TryInto;
-use std::iter::{ExactSizeIterator, IntoIterator, Iterator};
-use std::str;
+use super::allocator::{Allocator, Global};
+use super::thin_vec_in::ThinVecIn;
 
-use thin_vec::ThinVec;
+use core::convert::TryInto;
+use core::hash::{Hash, Hasher};
+use core::iter::{ExactSizeIterator, IntoIterator, Iterator};
+use core::str;
 
 /// Store any string efficiently in an immutable way.
 ///
 /// Can store at most `u32::MAX` strings and only provides
 /// `StringsNoIndexIter` and does not provide arbitary indexing.
-#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
-pub struct StringsNoIndex {
-    strs: ThinVec<u8>,
+///
+/// * `A` - Allocator of the strings.
+#[derive(Debug, Default, Clone)]
+pub struct StringsNoIndex<A: Allocator = Global> {
+    strs: ThinVecIn<u8, A>,
 }
 
 impl StringsNoIndex {
@@ -18,12 +22,33 @@ impl StringsNoIndex {
         Self::default()
     }
 
+    /// * `len` - number of strings
+    pub fn with_capacity(len: u32) -> Self {
+        Self::with_capacity_in(len, Global)
+    }
+}
+
+impl<A: Allocator> StringsNoIndex<A> {
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            strs: ThinVecIn::new_in(alloc),
+        }
+    }
+
     /// * `len` - number of strings
     ///
-    /// NOTE that this function does nothing and is defined just to be compatible
-    /// with `Strings`.
-    pub fn with_capacity(_len: u32) -> Self {
-        Self::new()
+    /// Reserves the length prefix and the null terminators of `len` strings,
+    /// use `reserve_strs` to reserve room for their bytes.
+    pub fn with_capacity_in(len: u32, alloc: A) -> Self {
+        let mut strings = Self::new_in(alloc);
+        if len != 0 {
+            strings.reserve_strs(4 + len as usize);
+        }
+        strings
+    }
+
+    pub fn allocator(&self) -> &A {
+        self.strs.allocator()
     }
 
     fn set_len(&mut self, new_len: u32) {
@@ -65,12 +90,25 @@ impl StringsNoIndex {
         self.strs.push(0);
     }
 
-    /// Accumulate length of all strings.
+    /// Length of the underlying buffer, which also stores the 4-byte length
+    /// prefix and a null terminator after each string.
+    ///
+    /// Unlike `Strings::strs_len`, this is not the accumulated length of
+    /// the strings, use `StringsNoIndex::content_len` for that.
     #[inline(always)]
     pub fn strs_len(&self) -> usize {
         self.strs.len()
     }
 
+    /// Accumulate length of all strings.
+    pub fn content_len(&self) -> usize {
+        if self.is_empty() {
+            0
+        } else {
+            self.strs.len() - 4 - self.len() as usize
+        }
+    }
+
     #[inline(always)]
     pub fn reserve_strs(&mut self, cnt: usize) {
         self.strs.reserve(cnt);
@@ -90,7 +128,21 @@ impl StringsNoIndex {
         StringsNoIndexIter::new(slice, self.len())
     }
 }
-impl<'a> IntoIterator for &'a StringsNoIndex {
+impl<A: Allocator> PartialEq for StringsNoIndex<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.strs == other.strs
+    }
+}
+
+impl<A: Allocator> Eq for StringsNoIndex<A> {}
+
+impl<A: Allocator> Hash for StringsNoIndex<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.strs.hash(state);
+    }
+}
+
+impl<'a, A: Allocator> IntoIterator for &'a StringsNoIndex<A> {
     type Item = &'a str;
     type IntoIter = StringsNoIndexIter<'a>;
 
@@ -160,6 +212,8 @@ mod tests {
         assert!(!strs.is_empty());
 
         assert!(input_strs.iter().eq(strs.iter()));
+        assert_eq!(strs.content_len(), input_strs.concat().len());
+        assert_eq!(strs.strs_len(), 4 + strs.content_len() + input_strs.len());
     }
 
     #[test]
@@ -167,6 +221,7 @@ mod tests {
         let mut strs = StringsNoIndex::new();
 
         assert!(strs.is_empty());
+        assert_eq!(strs.content_len(), 0);
 
         for i in 0..10 {
             strs.push("");
@@ -185,4 +240,37 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_with_capacity() {
+        let strs = StringsNoIndex::with_capacity(0);
+        assert_eq!(strs.strs.capacity(), 0);
+
+        let mut strs = StringsNoIndex::with_capacity(10);
+        let capacity = strs.strs.capacity();
+        assert!(capacity >= 4 + 10);
+
+        for _ in 0..10 {
+            strs.push("");
+        }
+        assert_eq!(strs.strs.capacity(), capacity);
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn test_allocator() {
+        use crate::allocator::tests::CountingAllocator;
+
+        let alloc = CountingAllocator::default();
+        let mut strs = super::StringsNoIndex::new_in(&alloc);
+        assert_eq!(alloc.live(), 0);
+
+        strs.push("1");
+        strs.push("23");
+        assert_eq!(alloc.live(), 1);
+        assert!(strs.iter().eq(["1", "23"]));
+
+        drop(strs);
+        assert_eq!(alloc.live(), 0);
+    }
 }
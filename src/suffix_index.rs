@@ -0,0 +1,404 @@
+use super::allocator::Allocator;
+use super::Strings;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use core::cmp::Ordering;
+use core::convert::TryInto;
+use core::ops::Range;
+
+/// Suffix array over the strings of a `Strings`, answering substring
+/// queries in `O(log n * (m + log len))` instead of scanning all strings,
+/// where `n` is `strs_len()`, `len` is `len()` and `m` is the length of the
+/// pattern.
+///
+/// Only the sorted suffix positions are stored, the `Strings` it is built
+/// from must be passed to every query.
+///
+/// It is built with SA-IS over `as_str()` with a separator appended to
+/// every string, so that no match spans more than one string.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Hash)]
+pub struct SuffixIndex {
+    /// Number of strings of the indexed `Strings`.
+    len: u32,
+    /// Byte offsets into `as_str()` in the order of their suffixes.
+    positions: Vec<u32>,
+}
+
+impl SuffixIndex {
+    /// Build the index in `O(strs_len + len)`.
+    pub fn new<A: Allocator>(strings: &Strings<A>) -> Self {
+        let (strs, ends) = strings.raw_parts();
+
+        let text_len = strs.len() + ends.len();
+        let _: u32 = text_len
+            .try_into()
+            .expect("SuffixIndex cannot index more than u32::MAX bytes and strings");
+
+        // Bytes are shifted by one and each string is followed by 0,
+        // which sorts before any byte and never matches a pattern.
+        let mut text = Vec::with_capacity(text_len);
+        let mut start = 0;
+        for end in ends {
+            text.extend(
+                strs[start..(*end as usize)]
+                    .iter()
+                    .map(|byte| *byte as u32 + 1),
+            );
+            text.push(0);
+            start = *end as usize;
+        }
+
+        let sa = sa_is(&text, 256);
+
+        // Reuse `text` to map its positions back to `strs` by removing the
+        // separators before them, separators are mapped to `u32::MAX`,
+        // which is larger than any position.
+        let mut separators_before = 0;
+        for (pos, c) in text.iter_mut().enumerate() {
+            if *c == 0 {
+                *c = u32::MAX;
+                separators_before += 1;
+            } else {
+                *c = (pos - separators_before) as u32;
+            }
+        }
+
+        Self {
+            len: strings.len(),
+            positions: sa
+                .into_iter()
+                .map(|pos| text[pos])
+                .filter(|pos| *pos != u32::MAX)
+                .collect(),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_raw_parts(len: u32, positions: Vec<u32>) -> Self {
+        Self { len, positions }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn raw_parts(&self) -> (u32, &[u32]) {
+        (self.len, &self.positions)
+    }
+
+    /// Only checks the lengths, a different `Strings` with the same lengths
+    /// gives wrong results.
+    fn assert_built_from<A: Allocator>(&self, strings: &Strings<A>) {
+        assert!(
+            strings.len() == self.len && strings.strs_len() as usize == self.positions.len(),
+            "SuffixIndex is used with a Strings of different lengths than the one it is built from"
+        );
+    }
+
+    /// Compare the suffix at `pos`, truncated to the length of `pattern`,
+    /// with `pattern`.
+    ///
+    /// The end of the string containing `pos` is found by binary search
+    /// instead of being stored, which would double the size of the index.
+    fn cmp_suffix<A: Allocator>(strings: &Strings<A>, pos: u32, pattern: &[u8]) -> Ordering {
+        let (_index, range) = strings.entry_at_byte(pos as usize).unwrap();
+        let suffix = &strings.as_str().as_bytes()[(pos as usize)..range.end];
+
+        if suffix.len() >= pattern.len() {
+            suffix[..pattern.len()].cmp(pattern)
+        } else {
+            // The separator after `suffix` sorts before any byte.
+            suffix.cmp(&pattern[..suffix.len()]).then(Ordering::Less)
+        }
+    }
+
+    /// Return the range in `positions` of the suffixes starting with
+    /// `pattern`, `pattern` must not be empty.
+    fn find_range<A: Allocator>(&self, strings: &Strings<A>, pattern: &[u8]) -> Range<usize> {
+        self.assert_built_from(strings);
+
+        let start = self
+            .positions
+            .partition_point(|pos| Self::cmp_suffix(strings, *pos, pattern) == Ordering::Less);
+        let end = start
+            + self.positions[start..]
+                .partition_point(|pos| Self::cmp_suffix(strings, *pos, pattern) == Ordering::Equal);
+
+        start..end
+    }
+
+    /// Return the number of occurrences of `pattern` in all strings,
+    /// including overlapping ones.
+    ///
+    /// An empty `pattern` occurs once in every string.
+    ///
+    /// Panics if `strings` has a different `len()` or `strs_len()` than the
+    /// one `self` is built from.
+    pub fn count<A: Allocator>(&self, strings: &Strings<A>, pattern: &str) -> usize {
+        if pattern.is_empty() {
+            self.assert_built_from(strings);
+            return self.len as usize;
+        }
+
+        self.find_range(strings, pattern.as_bytes()).len()
+    }
+
+    /// Return the sorted indices of the strings containing `pattern`.
+    ///
+    /// Panics if `strings` has a different `len()` or `strs_len()` than the
+    /// one `self` is built from.
+    pub fn entries_containing<A: Allocator>(
+        &self,
+        strings: &Strings<A>,
+        pattern: &str,
+    ) -> Vec<u32> {
+        if pattern.is_empty() {
+            self.assert_built_from(strings);
+            return (0..self.len).collect();
+        }
+
+        let mut indices: Vec<u32> = self.positions[self.find_range(strings, pattern.as_bytes())]
+            .iter()
+            .map(|pos| strings.entry_at_byte(*pos as usize).unwrap().0)
+            .collect();
+
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+}
+
+const NONE: usize = usize::MAX;
+
+/// Return the suffix array of `s`, whose characters are in `0..=upper`.
+///
+/// SA-IS by Nong, Zhang and Chan, following the implementation in
+/// the AtCoder Library.
+fn sa_is(s: &[u32], upper: u32) -> Vec<usize> {
+    let n = s.len();
+    let upper = upper as usize;
+
+    if n < 10 {
+        let mut sa: Vec<usize> = (0..n).collect();
+        sa.sort_unstable_by(|a, b| s[*a..].cmp(&s[*b..]));
+        return sa;
+    }
+
+    // `ls[i]` is true if the suffix at `i` is S-type.
+    let mut ls = vec![false; n];
+    for i in (0..(n - 1)).rev() {
+        ls[i] = if s[i] == s[i + 1] {
+            ls[i + 1]
+        } else {
+            s[i] < s[i + 1]
+        };
+    }
+
+    let mut sum_l = vec![0; upper + 1];
+    let mut sum_s = vec![0; upper + 1];
+    for i in 0..n {
+        if !ls[i] {
+            sum_s[s[i] as usize] += 1;
+        } else {
+            sum_l[s[i] as usize + 1] += 1;
+        }
+    }
+    for i in 0..=upper {
+        sum_s[i] += sum_l[i];
+        if i < upper {
+            sum_l[i + 1] += sum_s[i];
+        }
+    }
+
+    let mut sa = vec![NONE; n];
+
+    let induce = |sa: &mut [usize], lms: &[usize]| {
+        sa.fill(NONE);
+
+        let mut buf = sum_s.clone();
+        for d in lms {
+            if *d == n {
+                continue;
+            }
+            let c = s[*d] as usize;
+            sa[buf[c]] = *d;
+            buf[c] += 1;
+        }
+
+        buf.copy_from_slice(&sum_l);
+        let c = s[n - 1] as usize;
+        sa[buf[c]] = n - 1;
+        buf[c] += 1;
+        for i in 0..n {
+            let v = sa[i];
+            if v != NONE && v >= 1 && !ls[v - 1] {
+                let c = s[v - 1] as usize;
+                sa[buf[c]] = v - 1;
+                buf[c] += 1;
+            }
+        }
+
+        buf.copy_from_slice(&sum_l);
+        for i in (0..n).rev() {
+            let v = sa[i];
+            if v != NONE && v >= 1 && ls[v - 1] {
+                let c = s[v - 1] as usize + 1;
+                buf[c] -= 1;
+                sa[buf[c]] = v - 1;
+            }
+        }
+    };
+
+    let mut lms_map = vec![NONE; n + 1];
+    let mut lms = Vec::new();
+    for i in 1..n {
+        if !ls[i - 1] && ls[i] {
+            lms_map[i] = lms.len();
+            lms.push(i);
+        }
+    }
+    let m = lms.len();
+
+    induce(&mut sa, &lms);
+
+    if m > 0 {
+        let mut sorted_lms: Vec<usize> =
+            sa.iter().copied().filter(|v| lms_map[*v] != NONE).collect();
+
+        let mut rec_s = vec![0; m];
+        let mut rec_upper = 0;
+        rec_s[lms_map[sorted_lms[0]]] = 0;
+
+        for i in 1..m {
+            let mut l = sorted_lms[i - 1];
+            let mut r = sorted_lms[i];
+            let end_l = if lms_map[l] + 1 < m {
+                lms[lms_map[l] + 1]
+            } else {
+                n
+            };
+            let end_r = if lms_map[r] + 1 < m {
+                lms[lms_map[r] + 1]
+            } else {
+                n
+            };
+
+            let mut same = true;
+            if end_l - l != end_r - r {
+                same = false;
+            } else {
+                while l < end_l {
+                    if s[l] != s[r] {
+                        break;
+                    }
+                    l += 1;
+                    r += 1;
+                }
+                if l == n || s[l] != s[r] {
+                    same = false;
+                }
+            }
+
+            if !same {
+                rec_upper += 1;
+            }
+            rec_s[lms_map[sorted_lms[i]]] = rec_upper;
+        }
+
+        let rec_sa = sa_is(&rec_s, rec_upper);
+
+        for i in 0..m {
+            sorted_lms[i] = lms[rec_sa[i]];
+        }
+        induce(&mut sa, &sorted_lms);
+    }
+
+    sa
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sa_is, Strings, SuffixIndex};
+
+    /// Deterministic pseudo random numbers.
+    fn lcg(seed: &mut u64) -> u32 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (*seed >> 33) as u32
+    }
+
+    #[test]
+    fn test_sa_is() {
+        let mut seed = 1;
+
+        for n in 0..300 {
+            for upper in [1, 3, 255] {
+                let s: Vec<u32> = (0..n).map(|_| lcg(&mut seed) % (upper + 1)).collect();
+
+                let mut expected: Vec<usize> = (0..s.len()).collect();
+                expected.sort_by(|a, b| s[*a..].cmp(&s[*b..]));
+
+                assert_eq!(sa_is(&s, upper), expected, "s = {:?}", s);
+            }
+        }
+    }
+
+    #[test]
+    fn test_suffix_index() {
+        let input = [
+            "parse_kv",
+            "",
+            "split_once",
+            "SmallArrayBox",
+            "parse",
+            "a",
+            "kvkv",
+            "once_cell",
+        ];
+        let strs: Strings = input.iter().collect();
+        let index = SuffixIndex::new(&strs);
+
+        for pattern in [
+            "parse",
+            "kv",
+            "once",
+            "a",
+            "s",
+            "_",
+            "ox",
+            "kvk",
+            "vp",
+            "eo",
+            "xyz",
+            "parse_kvsplit",
+        ] {
+            let overlapping: usize = input
+                .iter()
+                .map(|s| {
+                    (0..s.len())
+                        .filter(|i| s[*i..].starts_with(pattern))
+                        .count()
+                })
+                .sum();
+            assert_eq!(index.count(&strs, pattern), overlapping, "{:?}", pattern);
+
+            let entries: Vec<u32> = (0..input.len() as u32)
+                .filter(|i| input[*i as usize].contains(pattern))
+                .collect();
+            assert_eq!(index.entries_containing(&strs, pattern), entries);
+        }
+
+        assert_eq!(index.count(&strs, ""), input.len());
+        assert_eq!(index.entries_containing(&strs, "").len(), input.len());
+
+        let empty = Strings::new();
+        let index = SuffixIndex::new(&empty);
+        assert_eq!(index.count(&empty, "a"), 0);
+        assert!(index.entries_containing(&empty, "a").is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Strings of different lengths")]
+    fn test_suffix_index_different_len() {
+        let index = SuffixIndex::new(&["abc"].iter().collect::<Strings>());
+        index.count(&["abcd"].iter().collect::<Strings>(), "a");
+    }
+}
@@ -0,0 +1,135 @@
+use super::allocator::{allocate, reallocate, Allocator};
+
+use alloc::alloc::Layout;
+
+use core::marker::PhantomData;
+use core::ptr::{self, NonNull};
+
+/// Stored at the start of the heap allocation, followed by the elements.
+#[repr(C)]
+struct Header {
+    len: usize,
+    cap: usize,
+}
+
+/// Shared by all empty thin containers, so that they never allocate.
+static EMPTY_HEADER: Header = Header { len: 0, cap: 0 };
+
+/// Pointer to a `Header` followed by `cap` elements, shared by `ThinVecIn`
+/// and `ThinSmallArrayBox`.
+///
+/// It does not own the allocation, the containers free it with the
+/// allocator they store.
+pub(crate) struct HeaderPtr<T> {
+    ptr: NonNull<Header>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> HeaderPtr<T> {
+    /// Return the layout of the allocation and the offset of the elements.
+    fn layout(cap: usize) -> (Layout, usize) {
+        let (layout, offset) = Layout::new::<Header>()
+            .extend(Layout::array::<T>(cap).expect("capacity overflow"))
+            .expect("capacity overflow");
+
+        (layout.pad_to_align(), offset)
+    }
+
+    pub(crate) fn empty() -> Self {
+        Self {
+            ptr: NonNull::from(&EMPTY_HEADER),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Allocate room for `cap` elements from `alloc`, with length 0.
+    pub(crate) fn allocate_in<A: Allocator>(alloc: &A, cap: usize) -> Self {
+        let (layout, _offset) = Self::layout(cap);
+        let ptr = allocate(alloc, layout).cast::<Header>();
+
+        unsafe { ptr.as_ptr().write(Header { len: 0, cap }) };
+
+        Self {
+            ptr,
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn is_sentinel(&self) -> bool {
+        ptr::eq(self.ptr.as_ptr(), &EMPTY_HEADER)
+    }
+
+    #[inline(always)]
+    fn header(&self) -> &Header {
+        unsafe { self.ptr.as_ref() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn len(&self) -> usize {
+        self.header().len
+    }
+
+    #[inline(always)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.header().cap
+    }
+
+    pub(crate) fn data_ptr(&self) -> *mut T {
+        if self.is_sentinel() {
+            NonNull::dangling().as_ptr()
+        } else {
+            let (_layout, offset) = Self::layout(0);
+            unsafe { (self.ptr.as_ptr() as *mut u8).add(offset) as *mut T }
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `len` must not be larger than the capacity and the first `len`
+    /// elements must be initialized.
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
+        debug_assert!(len <= self.capacity());
+
+        if !self.is_sentinel() {
+            self.ptr.as_mut().len = len;
+        }
+    }
+
+    /// Reallocate to `new_cap`, which must not be smaller than the length.
+    ///
+    /// # Safety
+    ///
+    /// `self` must be the sentinel or allocated by `alloc`.
+    pub(crate) unsafe fn reallocate_in<A: Allocator>(&mut self, alloc: &A, new_cap: usize) {
+        debug_assert!(new_cap >= self.len());
+
+        if self.is_sentinel() {
+            *self = Self::allocate_in(alloc, new_cap);
+            return;
+        }
+
+        let len = self.len();
+        let (old_layout, _offset) = Self::layout(self.capacity());
+        let (new_layout, _offset) = Self::layout(new_cap);
+
+        let ptr = reallocate(alloc, self.ptr.cast(), old_layout, new_layout).cast::<Header>();
+        ptr.as_ptr().write(Header { len, cap: new_cap });
+
+        self.ptr = ptr;
+    }
+
+    /// Free the allocation without dropping the elements and reset `self`
+    /// to the sentinel.
+    ///
+    /// # Safety
+    ///
+    /// `self` must be the sentinel or allocated by `alloc`.
+    pub(crate) unsafe fn deallocate_in<A: Allocator>(&mut self, alloc: &A) {
+        if !self.is_sentinel() {
+            let (layout, _offset) = Self::layout(self.capacity());
+            alloc.deallocate(self.ptr.cast(), layout);
+
+            *self = Self::empty();
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use super::allocator::Global;
+use super::thin_header::HeaderPtr;
+use super::SmallArrayBox;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use core::mem::ManuallyDrop;
+use core::ptr;
+use core::slice::{self, from_raw_parts, from_raw_parts_mut};
+
+use core::iter::{ExactSizeIterator, FromIterator, IntoIterator, Iterator};
+
+use core::borrow::{Borrow, BorrowMut};
+use core::fmt::{self, Debug};
+use core::hash::{Hash, Hasher};
+use core::ops::{Deref, DerefMut};
+
+use core::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
+
+/// Pointer-sized alternative to `SmallArrayBox` that stores the length
+/// on the heap next to the elements, like `ThinVec`.
+///
+/// Empty arrays point to a static sentinel and do not allocate.
+pub struct ThinSmallArrayBox<T> {
+    /// Its capacity is always its length.
+    ptr: HeaderPtr<T>,
+}
+
+unsafe impl<T: Send> Send for ThinSmallArrayBox<T> {}
+unsafe impl<T: Sync> Sync for ThinSmallArrayBox<T> {}
+
+impl<T> Default for ThinSmallArrayBox<T> {
+    fn default() -> Self {
+        Self::new_empty()
+    }
+}
+
+impl<T> ThinSmallArrayBox<T> {
+    pub fn new_empty() -> Self {
+        Self {
+            ptr: HeaderPtr::empty(),
+        }
+    }
+
+    /// The header and `iter.len()` elements are allocated once and the
+    /// elements are written in place.
+    ///
+    /// The allocation is grown or shrunk if `iter` yields more or fewer
+    /// elements than it claims.
+    pub fn new<I>(iter: impl IntoIterator<IntoIter = I>) -> Self
+    where
+        I: Iterator<Item = T> + ExactSizeIterator,
+    {
+        let iter = iter.into_iter();
+        let len = iter.len();
+        Self::from_iter_with_cap(iter, len)
+    }
+
+    fn from_iter_with_cap(iter: impl Iterator<Item = T>, cap: usize) -> Self {
+        // `this` also acts as the drop guard: if `iter` panics, the
+        // elements written so far are dropped and the allocation is freed.
+        let mut this = Self::new_empty();
+        if cap != 0 {
+            this.ptr = HeaderPtr::allocate_in(&Global, cap);
+        }
+
+        for elem in iter {
+            let len = this.raw_len();
+
+            if len == this.ptr.capacity() {
+                let new_cap = len.checked_mul(2).expect("capacity overflow").max(4);
+                unsafe { this.ptr.reallocate_in(&Global, new_cap) };
+            }
+
+            unsafe {
+                this.data_ptr().add(len).write(elem);
+                this.ptr.set_len(len + 1);
+            }
+        }
+
+        let len = this.raw_len();
+        if len != this.ptr.capacity() {
+            unsafe {
+                if len == 0 {
+                    this.ptr.deallocate_in(&Global);
+                } else {
+                    this.ptr.reallocate_in(&Global, len);
+                }
+            }
+        }
+
+        this
+    }
+
+    #[cfg(test)]
+    fn is_sentinel(&self) -> bool {
+        self.ptr.is_sentinel()
+    }
+
+    #[inline(always)]
+    fn raw_len(&self) -> usize {
+        self.ptr.len()
+    }
+
+    fn data_ptr(&self) -> *mut T {
+        self.ptr.data_ptr()
+    }
+
+    pub fn from_box(boxed: Box<[T]>) -> Self {
+        Self::new(boxed.into_vec())
+    }
+
+    pub fn into_boxed_slice(self) -> Box<[T]> {
+        self.into_vec().into_boxed_slice()
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        let mut this = ManuallyDrop::new(self);
+
+        let len = this.raw_len();
+        let mut vec = Vec::with_capacity(len);
+
+        unsafe {
+            ptr::copy_nonoverlapping(this.data_ptr(), vec.as_mut_ptr(), len);
+            vec.set_len(len);
+
+            // The elements are moved to `vec`, only the allocation is freed.
+            this.dealloc();
+        }
+
+        vec
+    }
+
+    /// # Safety
+    ///
+    /// Elements must have been moved out or dropped.
+    unsafe fn dealloc(&mut self) {
+        self.ptr.deallocate_in(&Global);
+    }
+}
+
+impl<T> From<Vec<T>> for ThinSmallArrayBox<T> {
+    fn from(vec: Vec<T>) -> Self {
+        Self::new(vec)
+    }
+}
+
+impl<T> From<Box<[T]>> for ThinSmallArrayBox<T> {
+    fn from(boxed: Box<[T]>) -> Self {
+        Self::from_box(boxed)
+    }
+}
+
+impl<T, const M: usize> From<[T; M]> for ThinSmallArrayBox<T> {
+    fn from(array: [T; M]) -> Self {
+        Self::new(IntoIterator::into_iter(array))
+    }
+}
+
+impl<T: Clone> From<&[T]> for ThinSmallArrayBox<T> {
+    fn from(slice: &[T]) -> Self {
+        Self::new(slice.iter().cloned())
+    }
+}
+
+impl<T, const INLINE_LEN: usize> From<SmallArrayBox<T, INLINE_LEN>> for ThinSmallArrayBox<T> {
+    fn from(array: SmallArrayBox<T, INLINE_LEN>) -> Self {
+        Self::new(array)
+    }
+}
+
+impl<T, const INLINE_LEN: usize> From<ThinSmallArrayBox<T>> for SmallArrayBox<T, INLINE_LEN> {
+    fn from(array: ThinSmallArrayBox<T>) -> Self {
+        array.into_vec().into()
+    }
+}
+
+impl<T> From<ThinSmallArrayBox<T>> for Box<[T]> {
+    fn from(array: ThinSmallArrayBox<T>) -> Self {
+        array.into_boxed_slice()
+    }
+}
+
+impl<T> From<ThinSmallArrayBox<T>> for Vec<T> {
+    fn from(array: ThinSmallArrayBox<T>) -> Self {
+        array.into_vec()
+    }
+}
+
+impl<T> FromIterator<T> for ThinSmallArrayBox<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let cap = iter.size_hint().0;
+        Self::from_iter_with_cap(iter, cap)
+    }
+}
+
+impl<T: Clone> Clone for ThinSmallArrayBox<T> {
+    fn clone(&self) -> Self {
+        self.deref().into()
+    }
+}
+
+impl<T> Deref for ThinSmallArrayBox<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { from_raw_parts(self.data_ptr(), self.raw_len()) }
+    }
+}
+
+impl<T> DerefMut for ThinSmallArrayBox<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { from_raw_parts_mut(self.data_ptr(), self.raw_len()) }
+    }
+}
+
+impl<T> Drop for ThinSmallArrayBox<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.deref_mut());
+            self.dealloc();
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ThinSmallArrayBox<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut ThinSmallArrayBox<T> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: Debug> Debug for ThinSmallArrayBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#?}", self.deref())
+    }
+}
+
+impl<T: PartialEq> PartialEq for ThinSmallArrayBox<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deref().eq(other.deref())
+    }
+}
+
+impl<T: Eq> Eq for ThinSmallArrayBox<T> {}
+
+impl<T, U> PartialEq<[U]> for ThinSmallArrayBox<T>
+where
+    T: PartialEq<U>,
+{
+    fn eq(&self, other: &[U]) -> bool {
+        self.deref().eq(other)
+    }
+}
+
+impl<T: PartialOrd> PartialOrd for ThinSmallArrayBox<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.deref().partial_cmp(other.deref())
+    }
+}
+
+impl<T: Ord> Ord for ThinSmallArrayBox<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deref().cmp(other.deref())
+    }
+}
+
+/// Same as the hash of `[T]`, so that `Borrow<[T]>` can be used to look up
+/// `ThinSmallArrayBox` in `HashMap` and `HashSet`.
+impl<T: Hash> Hash for ThinSmallArrayBox<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.deref().hash(state)
+    }
+}
+
+impl<T> AsRef<[T]> for ThinSmallArrayBox<T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> AsMut<[T]> for ThinSmallArrayBox<T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T> Borrow<[T]> for ThinSmallArrayBox<T> {
+    fn borrow(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T> BorrowMut<[T]> for ThinSmallArrayBox<T> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    type ThinSmallArrayBox = super::ThinSmallArrayBox<u8>;
+
+    use std::mem::size_of;
+    use std::ops::{Deref, DerefMut};
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    #[test]
+    fn test_size() {
+        assert_eq!(size_of::<ThinSmallArrayBox>(), size_of::<usize>());
+        assert_eq!(size_of::<Option<ThinSmallArrayBox>>(), size_of::<usize>());
+    }
+
+    #[test]
+    fn test_new_empty() {
+        let mut empty_array = ThinSmallArrayBox::new_empty();
+
+        let empty: &[u8] = &[];
+
+        assert_eq!(empty_array.deref(), empty);
+        assert_eq!(empty_array.deref_mut(), empty);
+        assert!(empty_array.is_sentinel());
+        assert!(ThinSmallArrayBox::new([]).is_sentinel());
+        assert!(ThinSmallArrayBox::from(Vec::new()).is_sentinel());
+
+        assert_eq!(&*empty_array.into_boxed_slice(), empty);
+    }
+
+    #[test]
+    fn test_new() {
+        let vec: Vec<u8> = (0..100).collect();
+
+        for len in 0..vec.len() {
+            let slice = &vec[..len];
+
+            let mut array = ThinSmallArrayBox::new(slice.iter().copied());
+
+            assert_eq!(array.deref(), slice);
+            assert_eq!(array.deref_mut(), slice);
+            assert_eq!(array.clone(), array);
+
+            let array2: ThinSmallArrayBox = slice.iter().copied().collect();
+            assert_eq!(array2, array);
+
+            let boxed = array.into_boxed_slice();
+            assert_eq!(&*boxed, slice);
+
+            let array = ThinSmallArrayBox::from_box(boxed);
+            assert_eq!(array.deref(), slice);
+
+            let array: super::SmallArrayBox<u8, 8> = array.into();
+            assert_eq!(array.deref(), slice);
+
+            let array = ThinSmallArrayBox::from(array);
+            assert_eq!(array.deref(), slice);
+        }
+    }
+
+    #[test]
+    fn test_alignment() {
+        let array = super::ThinSmallArrayBox::<u128>::from([1, 2, 3]);
+        assert_eq!(array.as_ptr() as usize % std::mem::align_of::<u128>(), 0);
+        assert_eq!(array.deref(), &[1, 2, 3]);
+
+        let array = super::ThinSmallArrayBox::<()>::from([(), ()]);
+        assert_eq!(array.len(), 2);
+    }
+
+    #[test]
+    fn test_drop() {
+        let rc = Rc::new(());
+
+        let array = super::ThinSmallArrayBox::new((0..10).map(|_| rc.clone()));
+        assert_eq!(Rc::strong_count(&rc), 11);
+
+        let vec = array.clone().into_vec();
+        assert_eq!(Rc::strong_count(&rc), 21);
+
+        drop(array);
+        drop(vec);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn test_from_iter_unknown_len() {
+        for len in [0, 1, 4, 5, 100] {
+            let array: ThinSmallArrayBox = (0..200).filter(|n| n % 2 == 0).take(len).collect();
+
+            assert!(array.iter().copied().eq((0..).step_by(2).take(len)));
+            assert_eq!(array.ptr.capacity(), len);
+            assert_eq!(array.is_sentinel(), len == 0);
+        }
+    }
+
+    #[test]
+    fn test_new_panicking_iter() {
+        let rc = Rc::new(());
+
+        for panic_at in [0, 3, 10] {
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                super::ThinSmallArrayBox::new((0..10).map(|n| {
+                    if n == panic_at {
+                        panic!("iter panics");
+                    }
+                    rc.clone()
+                }))
+            }));
+
+            assert_eq!(res.is_err(), panic_at < 10);
+            drop(res);
+            assert_eq!(Rc::strong_count(&rc), 1);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use super::allocator::{Allocator, Global};
+use super::thin_header::HeaderPtr;
+
+use core::cmp;
+use core::fmt::{self, Debug};
+use core::hash::{Hash, Hasher};
+use core::ops::{Deref, DerefMut, Range};
+use core::ptr;
+use core::slice::{from_raw_parts, from_raw_parts_mut};
+
+/// Pointer-sized growable array that allocates from `A`, used as the
+/// storage of the strings containers.
+///
+/// Only `Copy` elements are supported, so that elements never need to
+/// be dropped.
+pub(crate) struct ThinVecIn<T: Copy, A: Allocator = Global> {
+    ptr: HeaderPtr<T>,
+    alloc: A,
+}
+
+unsafe impl<T: Copy + Send, A: Allocator + Send> Send for ThinVecIn<T, A> {}
+unsafe impl<T: Copy + Sync, A: Allocator + Sync> Sync for ThinVecIn<T, A> {}
+
+impl<T: Copy, A: Allocator> ThinVecIn<T, A> {
+    pub(crate) fn new_in(alloc: A) -> Self {
+        Self {
+            ptr: HeaderPtr::empty(),
+            alloc,
+        }
+    }
+
+    pub(crate) fn with_capacity_in(cap: usize, alloc: A) -> Self {
+        let mut vec = Self::new_in(alloc);
+        vec.reserve(cap);
+        vec
+    }
+
+    pub(crate) fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    #[inline(always)]
+    pub(crate) fn len(&self) -> usize {
+        self.ptr.len()
+    }
+
+    #[inline(always)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline(always)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.ptr.capacity()
+    }
+
+    fn data_ptr(&self) -> *mut T {
+        self.ptr.data_ptr()
+    }
+
+    /// * `len` - must not be larger than the capacity and the first `len`
+    ///   elements must be initialized.
+    unsafe fn set_len(&mut self, len: usize) {
+        self.ptr.set_len(len);
+    }
+
+    /// Reallocate to `new_cap`, which must not be smaller than the length.
+    fn realloc(&mut self, new_cap: usize) {
+        unsafe { self.ptr.reallocate_in(&self.alloc, new_cap) };
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        let cap = self.capacity();
+        let required = self
+            .len()
+            .checked_add(additional)
+            .expect("capacity overflow");
+
+        if required > cap {
+            self.realloc(cmp::max(cmp::max(required, cap * 2), 4));
+        }
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        let len = self.len();
+
+        if self.ptr.is_sentinel() || len == self.capacity() {
+            return;
+        }
+
+        if len == 0 {
+            unsafe { self.ptr.deallocate_in(&self.alloc) };
+        } else {
+            self.realloc(len);
+        }
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            unsafe { self.set_len(len) };
+        }
+    }
+
+    pub(crate) fn push(&mut self, elem: T) {
+        self.reserve(1);
+
+        let len = self.len();
+        unsafe {
+            self.data_ptr().add(len).write(elem);
+            self.set_len(len + 1);
+        }
+    }
+
+    pub(crate) fn extend_from_slice(&mut self, slice: &[T]) {
+        self.reserve(slice.len());
+
+        let len = self.len();
+        unsafe {
+            ptr::copy_nonoverlapping(slice.as_ptr(), self.data_ptr().add(len), slice.len());
+            self.set_len(len + slice.len());
+        }
+    }
+
+    pub(crate) fn extend(&mut self, iter: impl IntoIterator<Item = T>) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+
+    /// Replace the elements in `range` with `replace_with`, shifting the
+    /// following elements.
+    pub(crate) fn replace_range(&mut self, range: Range<usize>, replace_with: &[T]) {
+        let Range { start, end } = range;
+        let len = self.len();
+        assert!(start <= end && end <= len, "range out of bounds");
+
+        let new_len = len - (end - start) + replace_with.len();
+        self.reserve(new_len.saturating_sub(len));
+
+        unsafe {
+            let data = self.data_ptr();
+            ptr::copy(
+                data.add(end),
+                data.add(start + replace_with.len()),
+                len - end,
+            );
+            ptr::copy_nonoverlapping(replace_with.as_ptr(), data.add(start), replace_with.len());
+            self.set_len(new_len);
+        }
+    }
+}
+
+impl<T: Copy, A: Allocator + Default> Default for ThinVecIn<T, A> {
+    fn default() -> Self {
+        Self::new_in(A::default())
+    }
+}
+
+impl<T: Copy, A: Allocator + Clone> Clone for ThinVecIn<T, A> {
+    fn clone(&self) -> Self {
+        let mut vec = Self::with_capacity_in(self.len(), self.alloc.clone());
+        vec.extend_from_slice(self);
+        vec
+    }
+}
+
+impl<T: Copy, A: Allocator> Deref for ThinVecIn<T, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { from_raw_parts(self.data_ptr(), self.len()) }
+    }
+}
+
+impl<T: Copy, A: Allocator> DerefMut for ThinVecIn<T, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { from_raw_parts_mut(self.data_ptr(), self.len()) }
+    }
+}
+
+impl<T: Copy, A: Allocator> Drop for ThinVecIn<T, A> {
+    fn drop(&mut self) {
+        unsafe { self.ptr.deallocate_in(&self.alloc) };
+    }
+}
+
+impl<T: Copy + Debug, A: Allocator> Debug for ThinVecIn<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.deref(), f)
+    }
+}
+
+impl<T: Copy + PartialEq, A: Allocator> PartialEq for ThinVecIn<T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deref().eq(other.deref())
+    }
+}
+
+impl<T: Copy + Eq, A: Allocator> Eq for ThinVecIn<T, A> {}
+
+impl<T: Copy + Hash, A: Allocator> Hash for ThinVecIn<T, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.deref().hash(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    type ThinVecIn = super::ThinVecIn<u32>;
+
+    use super::Global;
+
+    #[test]
+    fn test() {
+        let mut vec = ThinVecIn::default();
+        assert!(vec.is_empty());
+        assert_eq!(vec.capacity(), 0);
+
+        for n in 0..100 {
+            vec.push(n);
+        }
+        vec.extend_from_slice(&[100, 101]);
+        vec.extend(102..200);
+
+        assert!(vec.iter().copied().eq(0..200));
+
+        vec.truncate(300);
+        assert_eq!(vec.len(), 200);
+        assert_eq!(vec.clone(), vec);
+
+        vec.shrink_to_fit();
+        assert_eq!(vec.capacity(), 200);
+
+        vec.replace_range(10..190, &[1000]);
+        assert!(vec
+            .iter()
+            .copied()
+            .eq((0..10).chain([1000]).chain(190..200)));
+
+        vec.replace_range(0..0, &[2000, 2001]);
+        vec.replace_range(13..13, &[]);
+        assert!(vec[..3].iter().copied().eq([2000, 2001, 0]));
+        assert_eq!(vec.len(), 23);
+
+        vec.replace_range(0..23, &[]);
+        assert!(vec.is_empty());
+
+        let mut vec = ThinVecIn::with_capacity_in(10, Global);
+        vec.shrink_to_fit();
+        assert_eq!(vec.capacity(), 0);
+        assert!(vec.is_empty());
+    }
+}
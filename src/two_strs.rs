@@ -1,10 +1,17 @@
+use super::allocator::{Allocator, Global};
+use super::SmallArrayBox;
+
 use core::fmt;
-use core::str;
+use core::hash::{Hash, Hasher};
+use core::iter;
+use core::ops::Deref;
+use core::str::{self, FromStr};
 
 /// Box of two strings.
 /// Store two strings efficiently in an immutable way.
-#[derive(Debug, Eq, PartialEq, Clone, Hash)]
-pub struct TwoStrs(Box<[u8]>);
+///
+/// * `A` - Allocator of the strings.
+pub struct TwoStrs<A: Allocator = Global>(SmallArrayBox<u8, 0, A>);
 
 impl From<(&str, &str)> for TwoStrs {
     fn from((s1, s2): (&str, &str)) -> Self {
@@ -12,33 +19,59 @@ impl From<(&str, &str)> for TwoStrs {
     }
 }
 
+/// Concatenate `s1` and `s2` with null byte in between into storage
+/// allocated from `alloc`, the null bytes in `s1` and `s2` are removed.
+fn concat_in<A: Allocator>(s1: &str, s2: &str, alloc: A) -> SmallArrayBox<u8, 0, A> {
+    let iter1 = s1.as_bytes().iter().copied().filter(|byte| *byte != b'\0');
+    let iter2 = s2.as_bytes().iter().copied().filter(|byte| *byte != b'\0');
+
+    let len = iter1.clone().count() + 1 + iter2.clone().count();
+
+    SmallArrayBox::from_iter_with_len_in(iter1.chain(iter::once(0)).chain(iter2), len, alloc)
+}
+
 impl TwoStrs {
     /// * `s1` - must not contain null byte.
     /// * `s2` - must not contain null byte.
     pub fn new(s1: &str, s2: &str) -> Self {
-        let iter1 = s1.as_bytes().iter().copied().filter(|byte| *byte != b'\0');
-        let iter2 = s2.as_bytes().iter().copied().filter(|byte| *byte != b'\0');
-
-        let len1 = iter1.clone().count();
-        let len2 = iter2.clone().count();
-
-        let mut bytes = Vec::with_capacity(len1 + 1 + len2);
+        Self::new_in(s1, s2, Global)
+    }
 
-        if len1 == s1.len() {
-            bytes.extend_from_slice(s1.as_bytes());
-        } else {
-            bytes.extend(iter1);
+    /// Split `s` on the first occurrence of `delim`, e.g. `user:password`.
+    ///
+    /// Returns an error if `s` does not contain `delim`.
+    pub fn split_once(s: &str, delim: char) -> Result<Self, ParseTwoStrsError> {
+        match s.split_once(delim) {
+            Some((s1, s2)) => Ok(Self::new(s1, s2)),
+            None => Err(ParseTwoStrsError { delim }),
         }
+    }
 
-        bytes.push(0);
+    /// Parse `key=value`, splitting on the first `=`.
+    pub fn parse_kv(s: &str) -> Result<Self, ParseTwoStrsError> {
+        Self::split_once(s, '=')
+    }
+}
 
-        if len2 == s2.len() {
-            bytes.extend_from_slice(s2.as_bytes());
-        } else {
-            bytes.extend(iter2);
-        }
+impl<A: Allocator> TwoStrs<A> {
+    /// Same as `TwoStrs::new`, except that the strings are stored in
+    /// memory allocated from `alloc`.
+    ///
+    /// * `s1` - must not contain null byte.
+    /// * `s2` - must not contain null byte.
+    pub fn new_in(s1: &str, s2: &str, alloc: A) -> Self {
+        Self(concat_in(s1, s2, alloc))
+    }
 
-        Self(bytes.into_boxed_slice())
+    pub fn allocator(&self) -> &A {
+        self.0.allocator()
+    }
+
+    /// Return a value implementing `Display` that writes the two strings
+    /// joined by `delim`, the reverse of `split_once`.
+    pub fn format_with<D: fmt::Display>(&self, delim: D) -> FormatWith<'_, D> {
+        let (s1, s2) = self.get();
+        FormatWith { s1, s2, delim }
     }
 
     pub fn get(&self) -> (&str, &str) {
@@ -51,16 +84,87 @@ impl TwoStrs {
     }
 }
 
-impl fmt::Display for TwoStrs {
+impl<A: Allocator + Clone> Clone for TwoStrs<A> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<A: Allocator> fmt::Debug for TwoStrs<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_tuple("TwoStrs").field(&self.0.deref()).finish()
+    }
+}
+
+impl<A: Allocator> PartialEq for TwoStrs<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<A: Allocator> Eq for TwoStrs<A> {}
+
+impl<A: Allocator> Hash for TwoStrs<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<A: Allocator> fmt::Display for TwoStrs<A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         let (s1, s2) = self.get();
         write!(f, "({}, {})", s1, s2)
     }
 }
 
+/// Same as `TwoStrs::parse_kv`.
+impl FromStr for TwoStrs {
+    type Err = ParseTwoStrsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_kv(s)
+    }
+}
+
+/// Returned by `TwoStrs::format_with`.
+#[derive(Debug, Clone)]
+pub struct FormatWith<'a, D> {
+    s1: &'a str,
+    s2: &'a str,
+    delim: D,
+}
+
+impl<D: fmt::Display> fmt::Display for FormatWith<'_, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}{}{}", self.s1, self.delim, self.s2)
+    }
+}
+
+/// The delimiter is not found in the string being parsed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ParseTwoStrsError {
+    delim: char,
+}
+
+impl ParseTwoStrsError {
+    /// The delimiter that is missing.
+    pub fn delim(&self) -> char {
+        self.delim
+    }
+}
+
+impl fmt::Display for ParseTwoStrsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "delimiter {:?} not found", self.delim)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseTwoStrsError {}
+
 #[cfg(test)]
 mod tests {
-    use super::TwoStrs;
+    use super::{ParseTwoStrsError, TwoStrs};
 
     fn assert(s1: &str, s2: &str) {
         let two_strs = TwoStrs::new(s1, s2);
@@ -83,4 +187,60 @@ mod tests {
         let two_strs = TwoStrs::new("1\023d\0", "\023e\0");
         assert_eq!(two_strs.get(), ("123d", "23e"));
     }
+
+    #[test]
+    fn test_split_once() {
+        let two_strs = TwoStrs::split_once("user:pass:word", ':').unwrap();
+        assert_eq!(two_strs.get(), ("user", "pass:word"));
+
+        let two_strs = TwoStrs::split_once(":", ':').unwrap();
+        assert_eq!(two_strs.get(), ("", ""));
+
+        let err = TwoStrs::split_once("user", ':').unwrap_err();
+        assert_eq!(err, ParseTwoStrsError { delim: ':' });
+        assert_eq!(err.delim(), ':');
+    }
+
+    #[test]
+    fn test_parse_kv() {
+        let two_strs = TwoStrs::parse_kv("PATH=/usr/bin:/bin").unwrap();
+        assert_eq!(two_strs.get(), ("PATH", "/usr/bin:/bin"));
+
+        let two_strs: TwoStrs = "a=b=c".parse().unwrap();
+        assert_eq!(two_strs.get(), ("a", "b=c"));
+
+        assert!("abc".parse::<TwoStrs>().is_err());
+    }
+
+    #[test]
+    fn test_format_with() {
+        let two_strs = TwoStrs::new("key", "value");
+
+        assert_eq!(two_strs.format_with('=').to_string(), "key=value");
+        assert_eq!(two_strs.format_with(": ").to_string(), "key: value");
+        assert_eq!(two_strs.to_string(), "(key, value)");
+
+        let line = two_strs.format_with(':').to_string();
+        assert_eq!(TwoStrs::split_once(&line, ':').unwrap(), two_strs);
+    }
+
+    #[cfg(feature = "allocator-api2")]
+    #[test]
+    fn test_allocator() {
+        use crate::allocator::tests::CountingAllocator;
+
+        let alloc = CountingAllocator::default();
+
+        let two_strs = TwoStrs::new_in("key", "value", &alloc);
+        assert_eq!(two_strs.get(), ("key", "value"));
+        assert_eq!(alloc.live(), 1);
+
+        let cloned = two_strs.clone();
+        assert_eq!(cloned, two_strs);
+        assert_eq!(alloc.live(), 2);
+
+        drop(two_strs);
+        drop(cloned);
+        assert_eq!(alloc.live(), 0);
+    }
 }